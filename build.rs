@@ -19,7 +19,7 @@ fn generate_egl_bindings() -> anyhow::Result<()> {
 fn generate_gl_bindings() -> anyhow::Result<()> {
     let out_dir = PathBuf::from(&env::var("OUT_DIR")?);
     let mut out_file = File::create(out_dir.join("gl_bindings.rs"))?;
-    Registry::new(Api::Gles2, (2, 0), Profile::Core, Fallbacks::None, [
+    Registry::new(Api::Gles2, (3, 0), Profile::Core, Fallbacks::None, [
         "GL_EXT_texture_format_BGRA8888",
     ])
     .write_bindings(gl_generator::StructGenerator, &mut out_file)?;
@@ -0,0 +1,690 @@
+use crate::{
+    fontprovider::{Font, FontProvider},
+    fonttexturecache::{FontTextureCache, FontTextureCacheContext},
+    genvec::Handle,
+    gfx::{DrawBuffer, Rect, RectFill, Rgba8, Vec2},
+    gl,
+    input::{Event, KeyboardEvent, KeyboardEventKind, PointerButton, PointerEventKind, Scancode},
+    label::{self, Label, LabelDrawData},
+    redact::{RedactMode, Redaction},
+};
+
+pub mod theme {
+    use crate::gfx::Rgba8;
+
+    pub const COLORS: &[(&str, Rgba8)] = &[
+        ("red", Rgba8::new(230, 36, 36, 255)),
+        ("yellow", Rgba8::new(250, 200, 20, 255)),
+        ("green", Rgba8::new(36, 176, 72, 255)),
+        ("blue", Rgba8::new(48, 92, 222, 255)),
+        ("black", Rgba8::new(0, 0, 0, 255)),
+        ("white", Rgba8::new(255, 255, 255, 255)),
+    ];
+    pub const STROKE_WIDTHS: &[f32] = &[2.0, 4.0, 8.0];
+    /// indexed the same way as stroke widths.
+    pub const FONT_SIZES: &[f32] = &[16.0, 24.0, 36.0];
+    pub const DEFAULT_SIZE_IDX: usize = 1;
+    pub const CARET_WIDTH: f32 = 2.0;
+    /// freehand points that are closer than this to the previous one are dropped.
+    pub const PEN_MIN_DISTANCE: f32 = 2.0;
+}
+
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
+pub enum Tool {
+    #[default]
+    Rect,
+    Ellipse,
+    Arrow,
+    Pen,
+    Text,
+    Pixelate,
+    Blur,
+}
+
+impl Tool {
+    fn name(&self) -> &'static str {
+        match self {
+            Self::Rect => "rectangle",
+            Self::Ellipse => "ellipse",
+            Self::Arrow => "arrow",
+            Self::Pen => "pen",
+            Self::Text => "text",
+            Self::Pixelate => "pixelate",
+            Self::Blur => "blur",
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum ShapeKind {
+    /// not normalized, the corner where drawing started is min.
+    Rect(Rect),
+    Ellipse(Rect),
+    Arrow {
+        from: Vec2,
+        to: Vec2,
+    },
+    Pen(Vec<Vec2>),
+    /// position is the top left corner, size is the font size.
+    Text {
+        position: Vec2,
+        text: String,
+        size: f32,
+    },
+    /// not normalized, see [`Annotate::redactions`].
+    Redact {
+        rect: Rect,
+        mode: RedactMode,
+    },
+}
+
+/// in global compositor coordinates.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Shape {
+    pub kind: ShapeKind,
+    pub color: Rgba8,
+    pub width: f32,
+}
+
+pub struct AnnotateDrawData<'a> {
+    pub font_provider: &'a mut FontProvider,
+    pub font_handle: Handle<Font>,
+    pub font_texture_cache: &'a mut FontTextureCache,
+    /// text annotations are tinted, see [`FontTextureCache::new_tinted`].
+    pub tinted_font_texture_cache: &'a mut FontTextureCache,
+    pub gl_lib: &'static gl::Lib,
+}
+
+impl Shape {
+    fn new(tool: Tool, position: Vec2, color: Rgba8, size_idx: usize) -> Self {
+        let width = theme::STROKE_WIDTHS[size_idx];
+        let kind = match tool {
+            Tool::Rect => ShapeKind::Rect(Rect::new(position, position)),
+            Tool::Ellipse => ShapeKind::Ellipse(Rect::new(position, position)),
+            Tool::Arrow => ShapeKind::Arrow {
+                from: position,
+                to: position,
+            },
+            Tool::Pen => ShapeKind::Pen(vec![position]),
+            Tool::Text => ShapeKind::Text {
+                position,
+                text: String::new(),
+                size: theme::FONT_SIZES[size_idx],
+            },
+            Tool::Pixelate => ShapeKind::Redact {
+                rect: Rect::new(position, position),
+                mode: RedactMode::Pixelate,
+            },
+            Tool::Blur => ShapeKind::Redact {
+                rect: Rect::new(position, position),
+                mode: RedactMode::Blur,
+            },
+        };
+        Self { kind, color, width }
+    }
+
+    fn extend_to(&mut self, position: Vec2) {
+        match &mut self.kind {
+            ShapeKind::Rect(rect) | ShapeKind::Ellipse(rect) | ShapeKind::Redact { rect, .. } => {
+                rect.max = position
+            }
+            ShapeKind::Arrow { to, .. } => *to = position,
+            ShapeKind::Pen(points) => {
+                let last = *points.last().unwrap();
+                if (position - last).length() >= theme::PEN_MIN_DISTANCE {
+                    points.push(position);
+                }
+            }
+            ShapeKind::Text { .. } => {}
+        }
+    }
+
+    /// shapes that were merely clicked are discarded, except for pen dots.
+    fn is_empty(&self) -> bool {
+        match &self.kind {
+            ShapeKind::Rect(rect) | ShapeKind::Ellipse(rect) | ShapeKind::Redact { rect, .. } => {
+                let size = rect.size();
+                size.x.abs() < 1.0 || size.y.abs() < 1.0
+            }
+            ShapeKind::Arrow { from, to } => (*to - *from).length() < 1.0,
+            ShapeKind::Pen(_) => false,
+            ShapeKind::Text { text, .. } => text.trim().is_empty(),
+        }
+    }
+
+    /// draws relative to origin, scale converts logical pixels into target pixels (e.g. physical
+    /// pixels of an exported image).
+    fn draw(
+        &self,
+        draw_buffer: &mut DrawBuffer,
+        origin: Vec2,
+        scale: f32,
+        text_layout: &mut fontdue::layout::Layout,
+        data: &mut AnnotateDrawData,
+    ) {
+        let map = |position: Vec2| (position - origin) * scale;
+        let width = self.width * scale;
+        match &self.kind {
+            ShapeKind::Rect(rect) => {
+                let rect = Rect::new(map(rect.min), map(rect.max)).normalize();
+                draw_buffer.push_rect_outlined(rect, width, self.color);
+            }
+            ShapeKind::Ellipse(rect) => {
+                let rect = Rect::new(map(rect.min), map(rect.max)).normalize();
+                draw_buffer.push_ellipse_outlined(rect, width, self.color);
+            }
+            ShapeKind::Arrow { from, to } => {
+                draw_buffer.push_arrow(map(*from), map(*to), width, self.color)
+            }
+            ShapeKind::Pen(points) => {
+                if let [point] = points.as_slice() {
+                    draw_buffer.push_circle_filled(map(*point), width * 0.5, self.color);
+                } else {
+                    let points: Vec<Vec2> = points.iter().map(|point| map(*point)).collect();
+                    draw_buffer.push_polyline(&points, width, self.color, false);
+                }
+            }
+            ShapeKind::Text {
+                position,
+                text,
+                size,
+            } => {
+                // NOTE: text is rasterized at the target size rather than scaled to stay crisp.
+                let font_handle = data
+                    .font_provider
+                    .get_or_create_font_with_size(data.font_handle, (size * scale).round());
+                let font = data.font_provider.get_font(font_handle);
+                text_layout.reset(&fontdue::layout::LayoutSettings::default());
+                text_layout.append(
+                    &[&font.inner],
+                    &fontdue::layout::TextStyle::new(text, font.size, 0),
+                );
+
+                let min = map(*position);
+                for glyph in text_layout.glyphs().iter() {
+                    let (tex, x1, y1, x2, y2) = data.tinted_font_texture_cache.get_texture_for_char(
+                        font_handle,
+                        glyph.parent,
+                        &FontTextureCacheContext {
+                            font_provider: data.font_provider,
+                            gl_lib: data.gl_lib,
+                        },
+                    );
+
+                    let glyph_min = min + Vec2::new(glyph.x, glyph.y);
+                    let glyph_size = Vec2::new(glyph.width as f32, glyph.height as f32);
+                    draw_buffer.push_rect_tinted(
+                        Rect::new(glyph_min, glyph_min + glyph_size),
+                        tex.handle,
+                        Rect::new(Vec2::new(x1, y1), Vec2::new(x2, y2)),
+                        self.color,
+                    );
+                }
+            }
+            // NOTE: redactions are applied to pixels, see [`Redaction::apply`].
+            ShapeKind::Redact { .. } => {}
+        }
+    }
+}
+
+/// byte offset of the caret in text, moved by one character.
+fn move_caret(text: &str, caret: usize, forward: bool) -> usize {
+    if forward {
+        text[caret..]
+            .chars()
+            .next()
+            .map_or(caret, |ch| caret + ch.len_utf8())
+    } else {
+        text[..caret]
+            .chars()
+            .next_back()
+            .map_or(caret, |ch| caret - ch.len_utf8())
+    }
+}
+
+/// line and column (in characters) of the caret.
+fn caret_line_column(text: &str, caret: usize) -> (usize, usize) {
+    let before = &text[..caret];
+    let line = before.matches('\n').count();
+    let column = before
+        .rsplit('\n')
+        .next()
+        .unwrap_or_default()
+        .chars()
+        .count();
+    (line, column)
+}
+
+// NOTE: similarly to crop annotations are in global compositor coordinates so that they can span
+// multiple outputs.
+pub struct Annotate {
+    pub active: bool,
+    tool: Tool,
+    color_idx: usize,
+    /// indexes stroke widths and font sizes.
+    size_idx: usize,
+    pub shapes: Vec<Shape>,
+    /// the shape that is being drawn or the text that is being edited.
+    pending: Option<Shape>,
+    /// byte offset into the text that is being edited.
+    caret: usize,
+    text_layout: fontdue::layout::Layout,
+    label: Label,
+}
+
+impl Default for Annotate {
+    fn default() -> Self {
+        Self {
+            active: false,
+            tool: Tool::default(),
+            color_idx: 0,
+            size_idx: theme::DEFAULT_SIZE_IDX,
+            shapes: Vec::new(),
+            pending: None,
+            caret: 0,
+            text_layout: fontdue::layout::Layout::new(
+                fontdue::layout::CoordinateSystem::PositiveYDown,
+            ),
+            label: Label::default(),
+        }
+    }
+}
+
+impl Annotate {
+    pub fn toggle(&mut self) {
+        self.active = !self.active;
+        self.commit_text();
+        _ = self.pending.take();
+    }
+
+    fn color(&self) -> Rgba8 {
+        theme::COLORS[self.color_idx].1
+    }
+
+    pub fn is_editing_text(&self) -> bool {
+        matches!(
+            self.pending,
+            Some(Shape {
+                kind: ShapeKind::Text { .. },
+                ..
+            })
+        )
+    }
+
+    /// finishes editing text, empty text is discarded.
+    pub fn commit_text(&mut self) {
+        if !self.is_editing_text() {
+            return;
+        }
+        let shape = self.pending.take().unwrap();
+        if !shape.is_empty() {
+            self.shapes.push(shape);
+        }
+    }
+
+    fn update_text(&mut self, keyboard_event: &KeyboardEvent) {
+        let Some(Shape {
+            kind: ShapeKind::Text { text, .. },
+            ..
+        }) = self.pending.as_mut()
+        else {
+            return;
+        };
+        let (KeyboardEventKind::Press { scancode } | KeyboardEventKind::Repeat { scancode }) =
+            keyboard_event.kind
+        else {
+            return;
+        };
+        match scancode {
+            Scancode::Backspace => {
+                let caret = move_caret(text, self.caret, false);
+                text.replace_range(caret..self.caret, "");
+                self.caret = caret;
+            }
+            Scancode::Delete => {
+                let end = move_caret(text, self.caret, true);
+                text.replace_range(self.caret..end, "");
+            }
+            Scancode::Left => self.caret = move_caret(text, self.caret, false),
+            Scancode::Right => self.caret = move_caret(text, self.caret, true),
+            Scancode::Home => {
+                self.caret = text[..self.caret].rfind('\n').map_or(0, |i| i + 1);
+            }
+            Scancode::End => {
+                self.caret += text[self.caret..]
+                    .find('\n')
+                    .unwrap_or(text.len() - self.caret);
+            }
+            Scancode::Enter => {
+                text.insert(self.caret, '\n');
+                self.caret += 1;
+            }
+            _ => {
+                if let Some(typed) = keyboard_event.text.as_deref() {
+                    text.insert_str(self.caret, typed);
+                    self.caret += typed.len();
+                }
+            }
+        }
+    }
+
+    /// view rect is in global compositor coordinates, pointer positions are relative to it.
+    pub fn update(&mut self, event: &Event, view_rect: Rect) {
+        let Event::Pointer(pointer_event) = event else {
+            return;
+        };
+        let position = pointer_event.position + view_rect.min;
+        match pointer_event.kind {
+            PointerEventKind::Press {
+                button: PointerButton::Left,
+            } => {
+                self.commit_text();
+                let color = self.color();
+                self.pending = Some(Shape::new(self.tool, position, color, self.size_idx));
+                self.caret = 0;
+            }
+            PointerEventKind::Motion { .. } => {
+                if let Some(shape) = self.pending.as_mut() {
+                    shape.extend_to(position);
+                }
+            }
+            // NOTE: text stays pending until it is committed.
+            PointerEventKind::Release {
+                button: PointerButton::Left,
+            } if !self.is_editing_text() => {
+                if let Some(shape) = self.pending.take() {
+                    if !shape.is_empty() {
+                        self.shapes.push(shape);
+                    }
+                }
+            }
+            _ => {}
+        }
+    }
+
+    /// keyboard is not tied to the pointer, keys are handled regardless of which screen the
+    /// pointer is on.
+    pub fn update_keyboard(&mut self, keyboard_event: &KeyboardEvent) {
+        if keyboard_event.mods.ctrl {
+            return;
+        }
+        if self.is_editing_text() {
+            self.update_text(keyboard_event);
+            return;
+        }
+        let KeyboardEventKind::Press { scancode } = keyboard_event.kind else {
+            return;
+        };
+        match scancode {
+            Scancode::R => self.tool = Tool::Rect,
+            Scancode::O => self.tool = Tool::Ellipse,
+            Scancode::A => self.tool = Tool::Arrow,
+            Scancode::F => self.tool = Tool::Pen,
+            Scancode::T => self.tool = Tool::Text,
+            Scancode::P => self.tool = Tool::Pixelate,
+            Scancode::B => self.tool = Tool::Blur,
+            Scancode::C => self.color_idx = (self.color_idx + 1) % theme::COLORS.len(),
+            Scancode::W => self.size_idx = (self.size_idx + 1) % theme::STROKE_WIDTHS.len(),
+            _ => {}
+        }
+    }
+
+    /// draws all shapes relative to origin, scale converts logical pixels into target pixels (e.g.
+    /// physical pixels of an exported image).
+    pub fn draw(
+        &mut self,
+        draw_buffer: &mut DrawBuffer,
+        origin: Vec2,
+        scale: f32,
+        data: &mut AnnotateDrawData,
+    ) {
+        for shape in self.shapes.iter().chain(self.pending.iter()) {
+            shape.draw(draw_buffer, origin, scale, &mut self.text_layout, data);
+        }
+    }
+
+    /// redactions in the order they were made, including the one that is being drawn.
+    pub fn redactions(&self) -> impl Iterator<Item = Redaction> + '_ {
+        self.shapes
+            .iter()
+            .chain(self.pending.iter())
+            .filter_map(|shape| match shape.kind {
+                ShapeKind::Redact { rect, mode } => Some(Redaction {
+                    rect: rect.normalize(),
+                    mode,
+                }),
+                _ => None,
+            })
+    }
+
+    /// whether there are shapes other than redactions.
+    pub fn has_drawings(&self) -> bool {
+        self.shapes
+            .iter()
+            .chain(self.pending.iter())
+            .any(|shape| !matches!(shape.kind, ShapeKind::Redact { .. }))
+    }
+
+    fn draw_caret(&self, draw_buffer: &mut DrawBuffer, origin: Vec2, data: &mut AnnotateDrawData) {
+        let Some(Shape {
+            kind:
+                ShapeKind::Text {
+                    position,
+                    text,
+                    size,
+                },
+            color,
+            ..
+        }) = self.pending.as_ref()
+        else {
+            return;
+        };
+
+        // NOTE: the bundled font is monospaced, which makes caret placement trivial.
+        let font_handle = data
+            .font_provider
+            .get_or_create_font_with_size(data.font_handle, *size);
+        let font = &data.font_provider.get_font(font_handle).inner;
+        let advance = font.metrics('m', *size).advance_width.ceil();
+        let Some(line_metrics) = font.horizontal_line_metrics(*size) else {
+            return;
+        };
+
+        let (line, column) = caret_line_column(text, self.caret);
+        let min = *position - origin
+            + Vec2::new(
+                column as f32 * advance,
+                line as f32 * line_metrics.new_line_size.ceil(),
+            );
+        let height = line_metrics.ascent.ceil() - line_metrics.descent.ceil();
+        let max = min + Vec2::new(theme::CARET_WIDTH, height);
+        draw_buffer.push_rect_filled(Rect::new(min, max), RectFill::Color(*color));
+    }
+
+    /// draws the caret of the text that is being edited and current tool, color and size at the
+    /// top of the view; none of it belongs in exported images.
+    pub fn draw_ui(
+        &mut self,
+        draw_buffer: &mut DrawBuffer,
+        view_rect: Rect,
+        data: &mut AnnotateDrawData,
+    ) {
+        if !self.active {
+            return;
+        }
+
+        self.draw_caret(draw_buffer, view_rect.min, data);
+
+        let text = if self.is_editing_text() {
+            "text (esc: done)".to_string()
+        } else {
+            let tool = match self.tool {
+                Tool::Pixelate | Tool::Blur => self.tool.name().to_string(),
+                Tool::Text => format!(
+                    "text, {}, {}px",
+                    theme::COLORS[self.color_idx].0,
+                    theme::FONT_SIZES[self.size_idx]
+                ),
+                _ => format!(
+                    "{}, {}, {}px",
+                    self.tool.name(),
+                    theme::COLORS[self.color_idx].0,
+                    theme::STROKE_WIDTHS[self.size_idx]
+                ),
+            };
+            format!("{tool} (r o a f t p b: tool, c: color, w: size)")
+        };
+        let mut label_draw_data = LabelDrawData {
+            font_provider: data.font_provider,
+            font_handle: data.font_handle,
+            font_texture_cache: data.font_texture_cache,
+            gl_lib: data.gl_lib,
+        };
+        let size = self
+            .label
+            .layout(&text, Some(self.color()), &label_draw_data);
+        let min = Vec2::new((view_rect.width() - size.x) / 2.0, label::theme::PADDING);
+        self.label.draw(
+            draw_buffer,
+            Rect::new(min, min + size),
+            &mut label_draw_data,
+        );
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::input::{KeyboardEvent, KeyboardMods, PointerButtons, PointerEvent};
+
+    fn pointer(kind: PointerEventKind, x: f32, y: f32) -> Event {
+        Event::Pointer(PointerEvent {
+            kind,
+            surface_id: 0,
+            position: Vec2::new(x, y),
+            buttons: PointerButtons::default(),
+        })
+    }
+
+    fn press(x: f32, y: f32) -> Event {
+        let button = PointerButton::Left;
+        pointer(PointerEventKind::Press { button }, x, y)
+    }
+
+    fn motion(x: f32, y: f32) -> Event {
+        let delta = Vec2::ZERO;
+        pointer(PointerEventKind::Motion { delta }, x, y)
+    }
+
+    fn release(x: f32, y: f32) -> Event {
+        let button = PointerButton::Left;
+        pointer(PointerEventKind::Release { button }, x, y)
+    }
+
+    fn keyboard(kind: KeyboardEventKind, text: Option<&str>) -> KeyboardEvent {
+        KeyboardEvent {
+            kind,
+            surface_id: 0,
+            mods: KeyboardMods {
+                ctrl: false,
+                shift: false,
+            },
+            text: text.map(str::to_string),
+        }
+    }
+
+    fn key(scancode: Scancode) -> KeyboardEvent {
+        keyboard(KeyboardEventKind::Press { scancode }, None)
+    }
+
+    fn repeat(scancode: Scancode) -> KeyboardEvent {
+        keyboard(KeyboardEventKind::Repeat { scancode }, None)
+    }
+
+    fn type_text(text: &str) -> KeyboardEvent {
+        let scancode = Scancode::Unidentified(0);
+        keyboard(KeyboardEventKind::Press { scancode }, Some(text))
+    }
+
+    #[test]
+    fn test_draw_shapes() {
+        let view_rect = Rect::new(Vec2::new(100.0, 0.0), Vec2::new(200.0, 100.0));
+        let mut annotate = Annotate::default();
+
+        annotate.update_keyboard(&key(Scancode::A));
+        annotate.update(&press(10.0, 10.0), view_rect);
+        annotate.update(&motion(50.0, 10.0), view_rect);
+        annotate.update(&release(50.0, 10.0), view_rect);
+        assert_eq!(annotate.shapes.len(), 1);
+        let arrow = ShapeKind::Arrow {
+            from: Vec2::new(110.0, 10.0),
+            to: Vec2::new(150.0, 10.0),
+        };
+        assert_eq!(annotate.shapes[0].kind, arrow);
+
+        // NOTE: a click does not make a rectangle.
+        annotate.update_keyboard(&key(Scancode::R));
+        annotate.update(&press(10.0, 10.0), view_rect);
+        annotate.update(&release(10.0, 10.0), view_rect);
+        assert_eq!(annotate.shapes.len(), 1);
+
+        annotate.update_keyboard(&key(Scancode::F));
+        annotate.update_keyboard(&key(Scancode::C));
+        annotate.update(&press(0.0, 0.0), view_rect);
+        annotate.update(&motion(1.0, 0.0), view_rect);
+        annotate.update(&motion(5.0, 0.0), view_rect);
+        annotate.update(&release(5.0, 0.0), view_rect);
+        assert_eq!(annotate.shapes.len(), 2);
+        assert_eq!(annotate.shapes[1].color, theme::COLORS[1].1);
+        assert_eq!(
+            annotate.shapes[1].kind,
+            ShapeKind::Pen(vec![Vec2::new(100.0, 0.0), Vec2::new(105.0, 0.0)])
+        );
+    }
+
+    #[test]
+    fn test_edit_text() {
+        let view_rect = Rect::new(Vec2::ZERO, Vec2::new(100.0, 100.0));
+        let mut annotate = Annotate::default();
+
+        annotate.update_keyboard(&key(Scancode::T));
+        annotate.update(&press(10.0, 20.0), view_rect);
+        annotate.update(&release(10.0, 20.0), view_rect);
+        assert!(annotate.is_editing_text());
+
+        // NOTE: tool shortcuts are typed while editing.
+        for event in [
+            type_text("a"),
+            type_text("ö"),
+            key(Scancode::Left),
+            key(Scancode::Left),
+            type_text("r"),
+            key(Scancode::End),
+            key(Scancode::Enter),
+            type_text("x"),
+            type_text("z"),
+            key(Scancode::Backspace),
+            repeat(Scancode::Backspace),
+            key(Scancode::Home),
+            type_text("y"),
+        ] {
+            annotate.update_keyboard(&event);
+        }
+        assert_eq!(caret_line_column("raö\ny", annotate.caret), (1, 1));
+
+        // NOTE: pressing elsewhere commits the text.
+        annotate.update(&press(50.0, 50.0), view_rect);
+        assert_eq!(annotate.shapes.len(), 1);
+        let text = ShapeKind::Text {
+            position: Vec2::new(10.0, 20.0),
+            text: "raö\ny".to_string(),
+            size: theme::FONT_SIZES[theme::DEFAULT_SIZE_IDX],
+        };
+        assert_eq!(annotate.shapes[0].kind, text);
+
+        // NOTE: empty text is discarded.
+        annotate.commit_text();
+        assert_eq!(annotate.shapes.len(), 1);
+        assert!(!annotate.is_editing_text());
+    }
+}
@@ -0,0 +1,343 @@
+use std::{env, fs, io, path::PathBuf};
+
+use anyhow::{Context as _, anyhow};
+
+use crate::{
+    encoder::{self, EncodeOptions, ImageFormat},
+    geometry,
+    gfx::Rect,
+    picker::ColorFormat,
+    redact,
+    resample::{ExportScale, ResampleFilter},
+    stitch::StitchScale,
+    template,
+};
+
+pub const USAGE: &str = concat!(
+    "usage: bscreen [options]\n",
+    "\n",
+    "options:\n",
+    "  --cursor, --no-cursor  show or hide the cursor initially (config: cursor)\n",
+    "  --clipboard, --no-clipboard\n",
+    "                         offer copied images on the clipboard (config: clipboard)\n",
+    "  --primary-selection, --no-primary-selection\n",
+    "                         offer copied images on the primary selection, pasted with middle\n",
+    "                         click (config: primary-selection)\n",
+    "  --detach, --no-detach  serve the clipboard from a background process after copying, so\n",
+    "                         bscreen returns right away (config: detach)\n",
+    "  --select               print the selected region instead of capturing it and exit\n",
+    "  --select-format <format>\n",
+    "                         {x}, {y}, {width}, {height}, {geometry}, {output} and {scale};\n",
+    "                         default: {x},{y} {width}x{height} (config: select-format)\n",
+    "  --pick                 start in color picker mode (ctrl+i toggles it); click copies the\n",
+    "                         color, or prints it and exits when writing to stdout\n",
+    "  --color-format <hex|rgb|hsl|float>\n",
+    "                         how picked colors are copied (config: color-format)\n",
+    "  --pixelate-block-size <pixels>\n",
+    "                         logical size of blocks of the pixelate annotation tool; default: 12\n",
+    "                         (config: pixelate-block-size)\n",
+    "  -g, --geometry <X,Y WxH>\n",
+    "                         capture the region without the overlay\n",
+    "  -d, --desktop          capture all outputs stitched together\n",
+    "  -o, --output <name>    capture the named output\n",
+    "                         capture modes write the image to stdout unless --save or --copy\n",
+    "                         is given\n",
+    "  -o -                   same as --stdout\n",
+    "  --stdout               write the image to stdout; in the overlay instead of the clipboard,\n",
+    "                         exiting right away. the default in the overlay when stdout is a\n",
+    "                         pipe\n",
+    "  -c, --copy             with capture modes copy to the clipboard, requires data control\n",
+    "                         support (ext or wlr) in the compositor\n",
+    "  --stitch-scale <max|native>\n",
+    "                         upscale stitched outputs to the highest scale or keep their own\n",
+    "                         density, leaving black gaps next to denser outputs\n",
+    "                         (config: stitch-scale)\n",
+    "  --export-scale <physical|logical|<factor>x|<pixels>px>\n",
+    "                         size of exported images: as captured, one pixel per logical pixel,\n",
+    "                         a multiple of the logical size or downscaled to fit the given\n",
+    "                         number of pixels (config: export-scale)\n",
+    "  --resample-filter <lanczos|mitchell>\n",
+    "                         used when export scale changes the size (config: resample-filter)\n",
+    "  -s, --save             save to a file; with capture modes instead of writing to stdout,\n",
+    "                         in the overlay on copy as well (ctrl+s always saves)\n",
+    "  --output-dir <dir>     directory for relative filenames, defaults to $XDG_PICTURES_DIR\n",
+    "                         (config: output-dir)\n",
+    "  --filename <template>  strftime fields and {output}, {geometry}, {x}, {y}, {width},\n",
+    "                         {height}; default: %Y-%m-%d_%H%M%S_{output} (config: filename)\n",
+    "  -f, --format <png|jpeg|webp|qoi|ppm|pam|bmp>\n",
+    "                         image format, defaults to the filename extension or png; the\n",
+    "                         extension is appended unless the filename has a known one\n",
+    "                         (config: format)\n",
+    "  --png-compression <fast|default|best>\n",
+    "                         (config: png-compression)\n",
+    "  --png-filter <none|sub|up|avg|paeth|adaptive>\n",
+    "                         (config: png-filter)\n",
+    "  --jpeg-quality <1-100> (config: jpeg-quality)\n",
+    "  -h, --help             print this help and exit\n",
+    "\n",
+    "config is read from $XDG_CONFIG_HOME/bscreen/config, one `key = value` per line.\n",
+);
+
+#[derive(Debug)]
+pub struct Config {
+    /// whether the cursor is visible initially. captures are always made both with and without
+    /// the cursor, so it can be toggled in the overlay.
+    pub cursor: bool,
+    pub clipboard: bool,
+    pub primary_selection: bool,
+    pub detach: bool,
+
+    /// print the selected region in slurp format instead of capturing it.
+    pub select: bool,
+    /// see template module.
+    pub select_format: String,
+    /// start in color picker mode.
+    pub pick: bool,
+    pub color_format: ColorFormat,
+    /// in logical pixels.
+    pub pixelate_block_size: u32,
+
+    /// when set the region is captured non-interactively, compatible with slurp output.
+    pub geometry: Option<Rect>,
+    pub desktop: bool,
+    /// name of the output to capture, e.g. DP-1.
+    pub output: Option<String>,
+    /// write the image to stdout instead of offering it on the clipboard.
+    pub stdout: bool,
+    /// offer the captured image on the clipboard in non-interactive modes.
+    pub copy: bool,
+    /// applies when captures of multiple outputs are combined.
+    pub stitch_scale: StitchScale,
+    pub export_scale: ExportScale,
+    pub resample_filter: ResampleFilter,
+
+    pub save: bool,
+    pub output_dir: Option<PathBuf>,
+    /// see template module.
+    pub filename: String,
+    /// when not set the format is derived from the filename extension, or png.
+    pub format: Option<ImageFormat>,
+    pub encode_options: EncodeOptions,
+    pub help: bool,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Self {
+            cursor: true,
+            clipboard: true,
+            primary_selection: false,
+            detach: true,
+
+            select: false,
+            select_format: template::DEFAULT_SELECT_FORMAT.to_string(),
+            pick: false,
+            color_format: ColorFormat::default(),
+            pixelate_block_size: redact::theme::DEFAULT_BLOCK_SIZE,
+
+            geometry: None,
+            desktop: false,
+            output: None,
+            stdout: false,
+            copy: false,
+            stitch_scale: StitchScale::default(),
+            export_scale: ExportScale::default(),
+            resample_filter: ResampleFilter::default(),
+
+            save: false,
+            output_dir: None,
+            filename: template::DEFAULT_FILENAME.to_string(),
+            format: None,
+            encode_options: EncodeOptions::default(),
+            help: false,
+        }
+    }
+}
+
+fn parse_bool(value: &str) -> anyhow::Result<bool> {
+    match value {
+        "true" | "yes" | "on" | "1" => Ok(true),
+        "false" | "no" | "off" | "0" => Ok(false),
+        _ => Err(anyhow!("invalid boolean {value}")),
+    }
+}
+
+pub fn config_dir() -> Option<PathBuf> {
+    env::var_os("XDG_CONFIG_HOME")
+        .filter(|dir| !dir.is_empty())
+        .map(PathBuf::from)
+        .or_else(|| env::var_os("HOME").map(|home| PathBuf::from(home).join(".config")))
+}
+
+fn config_path() -> Option<PathBuf> {
+    Some(config_dir()?.join("bscreen").join("config"))
+}
+
+impl Config {
+    /// reads config file (if any) and then applies command line arguments on top of it.
+    pub fn load() -> anyhow::Result<Self> {
+        let mut config = Self::default();
+
+        if let Some(path) = config_path() {
+            match fs::read_to_string(&path) {
+                Ok(contents) => config
+                    .apply_file(&contents)
+                    .with_context(|| format!("invalid config {}", path.display()))?,
+                Err(err) if err.kind() == io::ErrorKind::NotFound => {}
+                Err(err) => {
+                    return Err(err).with_context(|| format!("could not read {}", path.display()));
+                }
+            }
+        }
+
+        config.apply_args(env::args().skip(1))?;
+
+        Ok(config)
+    }
+
+    fn set(&mut self, key: &str, value: &str) -> anyhow::Result<()> {
+        match key {
+            "cursor" => self.cursor = parse_bool(value)?,
+            "clipboard" => self.clipboard = parse_bool(value)?,
+            "primary-selection" => self.primary_selection = parse_bool(value)?,
+            "detach" => self.detach = parse_bool(value)?,
+            "select-format" => self.select_format = value.to_string(),
+            "color-format" => self.color_format = value.parse()?,
+            "pixelate-block-size" => self.pixelate_block_size = redact::parse_block_size(value)?,
+            "stitch-scale" => self.stitch_scale = value.parse()?,
+            "export-scale" => self.export_scale = value.parse()?,
+            "resample-filter" => self.resample_filter = value.parse()?,
+            "output-dir" => self.output_dir = Some(PathBuf::from(value)),
+            "filename" => self.filename = value.to_string(),
+            "format" => self.format = Some(value.parse()?),
+            "png-compression" => self.encode_options.png_compression = value.parse()?,
+            "png-filter" => self.encode_options.png_filter = value.parse()?,
+            "jpeg-quality" => {
+                self.encode_options.jpeg_quality = encoder::parse_jpeg_quality(value)?
+            }
+            _ => return Err(anyhow!("unknown key {key}")),
+        }
+        Ok(())
+    }
+
+    fn apply_file(&mut self, contents: &str) -> anyhow::Result<()> {
+        for (idx, line) in contents.lines().enumerate() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            let (key, value) = line
+                .split_once('=')
+                .with_context(|| format!("line {}: expected `key = value`", idx + 1))?;
+            self.set(key.trim(), value.trim())
+                .with_context(|| format!("line {}", idx + 1))?;
+        }
+        Ok(())
+    }
+
+    fn apply_args<I: IntoIterator<Item = String>>(&mut self, args: I) -> anyhow::Result<()> {
+        let mut args = args.into_iter();
+        while let Some(arg) = args.next() {
+            let mut value = || {
+                args.next()
+                    .with_context(|| format!("{arg} requires a value"))
+            };
+            match arg.as_str() {
+                "-h" | "--help" => self.help = true,
+                "--cursor" => self.cursor = true,
+                "--no-cursor" => self.cursor = false,
+                "--clipboard" => self.clipboard = true,
+                "--no-clipboard" => self.clipboard = false,
+                "--primary-selection" => self.primary_selection = true,
+                "--no-primary-selection" => self.primary_selection = false,
+                "--detach" => self.detach = true,
+                "--no-detach" => self.detach = false,
+                "--select" => self.select = true,
+                "--select-format" => self.select_format = value()?,
+                "--pick" => self.pick = true,
+                "--color-format" => self.color_format = value()?.parse()?,
+                "--pixelate-block-size" => {
+                    self.pixelate_block_size = redact::parse_block_size(&value()?)?
+                }
+                "-g" | "--geometry" => self.geometry = Some(geometry::parse(&value()?)?),
+                "-d" | "--desktop" => self.desktop = true,
+                "-o" | "--output" => match value()?.as_str() {
+                    "-" => self.stdout = true,
+                    name => self.output = Some(name.to_string()),
+                },
+                "--stdout" => self.stdout = true,
+                "-c" | "--copy" => self.copy = true,
+                "--stitch-scale" => self.stitch_scale = value()?.parse()?,
+                "--export-scale" => self.export_scale = value()?.parse()?,
+                "--resample-filter" => self.resample_filter = value()?.parse()?,
+                "-s" | "--save" => self.save = true,
+                "--output-dir" => self.output_dir = Some(PathBuf::from(value()?)),
+                "--filename" => self.filename = value()?,
+                "-f" | "--format" => self.format = Some(value()?.parse()?),
+                "--png-compression" => self.encode_options.png_compression = value()?.parse()?,
+                "--png-filter" => self.encode_options.png_filter = value()?.parse()?,
+                "--jpeg-quality" => {
+                    self.encode_options.jpeg_quality = encoder::parse_jpeg_quality(&value()?)?
+                }
+                _ => return Err(anyhow!("unknown argument {arg}")),
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn args(args: &[&str]) -> Vec<String> {
+        args.iter().map(|arg| arg.to_string()).collect()
+    }
+
+    #[test]
+    fn test_apply_file() {
+        let mut config = Config::default();
+        config
+            .apply_file("# comment\n\n  cursor = no  \nstitch-scale = native")
+            .unwrap();
+        assert!(!config.cursor);
+        assert_eq!(config.stitch_scale, StitchScale::Native);
+
+        assert!(config.apply_file("cursor").is_err());
+        assert!(config.apply_file("cursor = maybe").is_err());
+        assert!(config.apply_file("unknown = 1").is_err());
+    }
+
+    #[test]
+    fn test_args_override_file() {
+        let mut config = Config::default();
+        config.apply_file("cursor = false").unwrap();
+        config.apply_args(args(&["--cursor"])).unwrap();
+        assert!(config.cursor);
+
+        assert!(config.apply_args(args(&["--unknown"])).is_err());
+    }
+
+    #[test]
+    fn test_args_with_value() {
+        let mut config = Config::default();
+        config.apply_args(args(&["-g", "0,0 10x20"])).unwrap();
+        assert!(config.geometry.is_some());
+        config.apply_args(args(&["-o", "DP-1", "--stdout"])).unwrap();
+        assert!(config.stdout);
+        assert_eq!(config.output.as_deref(), Some("DP-1"));
+
+        let mut config = Config::default();
+        config.apply_args(args(&["-o", "-"])).unwrap();
+        assert!(config.stdout);
+        assert!(config.output.is_none());
+        config
+            .apply_args(args(&["-f", "jpg", "--jpeg-quality", "75"]))
+            .unwrap();
+        assert_eq!(config.format, Some(ImageFormat::Jpeg));
+        assert_eq!(config.encode_options.jpeg_quality, 75);
+        assert!(config.apply_args(args(&["--jpeg-quality", "0"])).is_err());
+
+        assert!(config.apply_args(args(&["--geometry"])).is_err());
+    }
+}
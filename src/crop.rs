@@ -70,42 +70,50 @@ fn pointer_on_handle(rect: &Rect, pointer_position: &Vec2) -> Option<HandleType>
     None
 }
 
+// NOTE: crop operates in global compositor coordinates so that a single selection can span
+// multiple outputs. pointer positions are surface-local, view rect's min is used to translate them.
 pub struct CropUpdateData {
+    /// rect of the pointer-focused view.
     pub view_rect: Rect,
+    /// union of all views, crop is constrained to it.
+    pub bounds: Rect,
 }
 
 #[derive(Debug, Default)]
 pub struct Crop {
-    pub view_rect: Option<Rect>,
     pub crop_rect: Option<Rect>,
     handle: Option<HandleType>,
     pub cursor: Option<CursorShape>,
 }
 
 impl Crop {
+    /// whether a handle is being dragged, i.e. the change of the crop rect is not finished.
+    pub fn is_dragging(&self) -> bool {
+        self.handle.is_some()
+    }
+
     pub fn update(&mut self, event: &Event, data: CropUpdateData) -> bool {
-        self.view_rect = Some(data.view_rect);
         let prev_crop_rect = self.crop_rect.clone();
 
         match event {
             Event::Pointer(pointer_event) => {
+                let pointer_position = pointer_event.position + data.view_rect.min;
                 match pointer_event.kind {
                     PointerEventKind::Press { .. } => {
                         if let Some(crop_rect) = self.crop_rect.as_ref() {
-                            self.handle = pointer_on_handle(crop_rect, &pointer_event.position);
+                            self.handle = pointer_on_handle(crop_rect, &pointer_position);
                             if self.handle.is_none() {
                                 _ = self.crop_rect.take();
                             }
                         }
                         if self.crop_rect.is_none() {
-                            self.crop_rect =
-                                Some(Rect::from_center_size(pointer_event.position, 0.0));
+                            self.crop_rect = Some(Rect::from_center_size(pointer_position, 0.0));
                             self.handle = Some(HandleType::BottomRight);
                         }
                     }
                     PointerEventKind::Release { .. } => {
                         if let Some(crop_rect) = self.crop_rect.as_mut() {
-                            *crop_rect = crop_rect.normalize().constrain_to(&data.view_rect);
+                            *crop_rect = crop_rect.normalize().constrain_to(&data.bounds);
                             let size = crop_rect.size();
                             if size.x < 1.0 || size.y < 1.0 {
                                 _ = self.crop_rect.take();
@@ -140,7 +148,7 @@ impl Crop {
                 self.cursor = Some(CursorShape::Crosshair);
                 if let Some(crop_rect) = self.crop_rect.as_ref() {
                     if let Some(handle) =
-                        pointer_on_handle(&crop_rect.normalize(), &pointer_event.position)
+                        pointer_on_handle(&crop_rect.normalize(), &pointer_position)
                     {
                         self.cursor = Some(handle.cursor_shape());
                     }
@@ -161,15 +169,22 @@ impl Crop {
         !prev_crop_rect.eq(&self.crop_rect)
     }
 
-    pub fn draw(&mut self, draw_buffer: &mut DrawBuffer) {
-        let Some(view_rect) = self.view_rect.as_ref() else {
+    /// view rect is in global compositor coordinates, everything is drawn relative to its min.
+    pub fn draw(&mut self, draw_buffer: &mut DrawBuffer, view_rect: Rect) {
+        let Some(crop_rect) = self.crop_rect.as_ref() else {
             return;
         };
-        let crop_rect = if let Some(crop_rect) = self.crop_rect.as_ref() {
-            crop_rect.normalize().constrain_to(&view_rect)
-        } else {
+        let offset = Vec2::ZERO - view_rect.min;
+        // NOTE: outline and handles may extend past the view when crop spans multiple views, they
+        // are clipped by the viewport.
+        let unconstrained_crop_rect = crop_rect.normalize().translate(&offset);
+        let view_rect = view_rect.translate(&offset);
+
+        if !unconstrained_crop_rect.intersects(&view_rect) {
+            draw_buffer.push_rect_filled(view_rect, RectFill::Color(theme::OUTSIDE_BG));
             return;
-        };
+        }
+        let crop_rect = unconstrained_crop_rect.constrain_to(&view_rect);
 
         // darkening rects
         // ----
@@ -209,7 +224,11 @@ impl Crop {
         // ----
 
         {
-            draw_buffer.push_rect_outlined(crop_rect, theme::OUTLINE_WIDTH, theme::OUTLINE);
+            draw_buffer.push_rect_outlined(
+                unconstrained_crop_rect,
+                theme::OUTLINE_WIDTH,
+                theme::OUTLINE,
+            );
         }
 
         // corner handles
@@ -219,25 +238,25 @@ impl Crop {
             let fill = RectFill::Color(theme::HANDLE_BG);
 
             draw_buffer.push_rect(
-                top_left_rect_handle(&crop_rect),
+                top_left_rect_handle(&unconstrained_crop_rect),
                 Some(fill),
                 Some(theme::OUTLINE_WIDTH),
                 Some(theme::OUTLINE),
             );
             draw_buffer.push_rect(
-                top_right_rect_handle(&crop_rect),
+                top_right_rect_handle(&unconstrained_crop_rect),
                 Some(fill),
                 Some(theme::OUTLINE_WIDTH),
                 Some(theme::OUTLINE),
             );
             draw_buffer.push_rect(
-                bottom_right_rect_handle(&crop_rect),
+                bottom_right_rect_handle(&unconstrained_crop_rect),
                 Some(fill),
                 Some(theme::OUTLINE_WIDTH),
                 Some(theme::OUTLINE),
             );
             draw_buffer.push_rect(
-                bottom_left_rect_handle(&crop_rect),
+                bottom_left_rect_handle(&unconstrained_crop_rect),
                 Some(fill),
                 Some(theme::OUTLINE_WIDTH),
                 Some(theme::OUTLINE),
@@ -1,4 +1,4 @@
-use std::ffi::{c_char, c_void};
+use std::ffi::{CStr, c_char, c_void};
 use std::mem::zeroed;
 use std::ops::Deref;
 use std::ptr::{null, null_mut};
@@ -128,6 +128,32 @@ impl Context {
         }
     }
 
+    /// checks whether the display advertises the given extension.
+    pub unsafe fn has_extension(&self, name: &str) -> bool {
+        let extensions = self.egl_lib.QueryString(self.display, sys::EXTENSIONS as _);
+        if extensions.is_null() {
+            return false;
+        }
+        CStr::from_ptr(extensions)
+            .to_bytes()
+            .split(|b| *b == b' ')
+            .any(|extension| extension == name.as_bytes())
+    }
+
+    /// releases the display along with everything the driver holds for it (e.g. its threads,
+    /// which do not survive fork). nothing that was created with the context can be used
+    /// afterwards.
+    pub unsafe fn terminate(&self) {
+        self.egl_lib.MakeCurrent(
+            self.display,
+            sys::NO_SURFACE,
+            sys::NO_SURFACE,
+            sys::NO_CONTEXT,
+        );
+        self.egl_lib.Terminate(self.display);
+        self.egl_lib.ReleaseThread();
+    }
+
     pub unsafe fn swap_buffers(&self, surface: sys::types::EGLSurface) -> anyhow::Result<()> {
         if self.egl_lib.SwapBuffers(self.display, surface) == sys::FALSE {
             Err(self.egl_lib.unwrap_err()).context("could not swap buffers")
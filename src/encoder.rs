@@ -0,0 +1,406 @@
+use std::{io::Write as _, path::Path};
+
+use anyhow::{Context as _, anyhow};
+
+use crate::gfx::Size;
+
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
+pub enum ImageFormat {
+    #[default]
+    Png,
+    Jpeg,
+    /// lossless.
+    Webp,
+    Qoi,
+    /// binary portable pixmap, alpha is dropped.
+    Ppm,
+    /// portable arbitrary map with alpha.
+    Pam,
+    Bmp,
+}
+
+impl std::str::FromStr for ImageFormat {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().as_str() {
+            "png" => Ok(Self::Png),
+            "jpeg" | "jpg" => Ok(Self::Jpeg),
+            "webp" => Ok(Self::Webp),
+            "qoi" => Ok(Self::Qoi),
+            "ppm" => Ok(Self::Ppm),
+            "pam" => Ok(Self::Pam),
+            "bmp" => Ok(Self::Bmp),
+            _ => Err(anyhow!(
+                "invalid format {s}, expected png, jpeg, webp, qoi, ppm, pam or bmp"
+            )),
+        }
+    }
+}
+
+impl ImageFormat {
+    pub fn from_path(path: &Path) -> Option<Self> {
+        path.extension()?.to_str()?.parse().ok()
+    }
+
+    pub fn extension(&self) -> &'static str {
+        match self {
+            Self::Png => "png",
+            Self::Jpeg => "jpg",
+            Self::Webp => "webp",
+            Self::Qoi => "qoi",
+            Self::Ppm => "ppm",
+            Self::Pam => "pam",
+            Self::Bmp => "bmp",
+        }
+    }
+
+    pub fn mime_type(&self) -> &'static str {
+        match self {
+            Self::Png => "image/png",
+            Self::Jpeg => "image/jpeg",
+            Self::Webp => "image/webp",
+            Self::Qoi => "image/qoi",
+            Self::Ppm => "image/x-portable-pixmap",
+            Self::Pam => "image/x-portable-arbitrarymap",
+            Self::Bmp => "image/bmp",
+        }
+    }
+}
+
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
+pub enum PngCompression {
+    #[default]
+    Fast,
+    Default,
+    Best,
+}
+
+impl std::str::FromStr for PngCompression {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "fast" => Ok(Self::Fast),
+            "default" => Ok(Self::Default),
+            "best" => Ok(Self::Best),
+            _ => Err(anyhow!(
+                "invalid png compression {s}, expected fast, default or best"
+            )),
+        }
+    }
+}
+
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
+pub enum PngFilter {
+    None,
+    #[default]
+    Sub,
+    Up,
+    Avg,
+    Paeth,
+    /// picks the best filter for each row, slower but usually smaller.
+    Adaptive,
+}
+
+impl std::str::FromStr for PngFilter {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "none" => Ok(Self::None),
+            "sub" => Ok(Self::Sub),
+            "up" => Ok(Self::Up),
+            "avg" => Ok(Self::Avg),
+            "paeth" => Ok(Self::Paeth),
+            "adaptive" => Ok(Self::Adaptive),
+            _ => Err(anyhow!(
+                "invalid png filter {s}, expected none, sub, up, avg, paeth or adaptive"
+            )),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct EncodeOptions {
+    pub png_compression: PngCompression,
+    pub png_filter: PngFilter,
+    /// 1..=100.
+    pub jpeg_quality: u8,
+}
+
+impl Default for EncodeOptions {
+    fn default() -> Self {
+        Self {
+            png_compression: PngCompression::default(),
+            png_filter: PngFilter::default(),
+            jpeg_quality: 90,
+        }
+    }
+}
+
+/// extra information stored alongside pixels, only png supports it.
+#[derive(Debug, Default, Clone)]
+pub struct ImageMetadata {
+    /// keyword and text pairs.
+    pub text: Vec<(String, String)>,
+    /// x and y.
+    pub pixels_per_meter: Option<(u32, u32)>,
+}
+
+pub struct Image {
+    /// tightly packed rgba8.
+    pub pixels: Vec<u8>,
+    pub size: Size,
+    pub metadata: ImageMetadata,
+}
+
+pub fn parse_jpeg_quality(value: &str) -> anyhow::Result<u8> {
+    match value.parse::<u8>() {
+        Ok(quality) if (1..=100).contains(&quality) => Ok(quality),
+        _ => Err(anyhow!("invalid jpeg quality {value}, expected 1..=100")),
+    }
+}
+
+fn encode_png(image: &Image, opts: &EncodeOptions) -> anyhow::Result<Vec<u8>> {
+    let mut data: Vec<u8> = Vec::new();
+    let mut encoder = png::Encoder::new(&mut data, image.size.width, image.size.height);
+    encoder.set_color(png::ColorType::Rgba);
+    encoder.set_depth(png::BitDepth::Eight);
+    encoder.set_compression(match opts.png_compression {
+        PngCompression::Fast => png::Compression::Fast,
+        PngCompression::Default => png::Compression::Default,
+        PngCompression::Best => png::Compression::Best,
+    });
+    match opts.png_filter {
+        PngFilter::None => encoder.set_filter(png::FilterType::NoFilter),
+        PngFilter::Sub => encoder.set_filter(png::FilterType::Sub),
+        PngFilter::Up => encoder.set_filter(png::FilterType::Up),
+        PngFilter::Avg => encoder.set_filter(png::FilterType::Avg),
+        PngFilter::Paeth => encoder.set_filter(png::FilterType::Paeth),
+        PngFilter::Adaptive => encoder.set_adaptive_filter(png::AdaptiveFilterType::Adaptive),
+    }
+
+    for (keyword, text) in image.metadata.text.iter() {
+        // NOTE: tEXt is latin-1, anything else needs iTXt.
+        if text.chars().all(|ch| (ch as u32) < 0x100) {
+            encoder.add_text_chunk(keyword.clone(), text.clone())
+        } else {
+            encoder.add_itxt_chunk(keyword.clone(), text.clone())
+        }
+        .with_context(|| format!("could not add png text {keyword}"))?;
+    }
+    if let Some((xppu, yppu)) = image.metadata.pixels_per_meter {
+        encoder.set_pixel_dims(Some(png::PixelDimensions {
+            xppu,
+            yppu,
+            unit: png::Unit::Meter,
+        }));
+    }
+
+    encoder
+        .write_header()
+        .context("could not write png header")?
+        .write_image_data(&image.pixels)
+        .context("could not write png data")?;
+    Ok(data)
+}
+
+fn encode_jpeg(pixels: &[u8], size: Size, opts: &EncodeOptions) -> anyhow::Result<Vec<u8>> {
+    let width = u16::try_from(size.width).context("image is too wide for jpeg")?;
+    let height = u16::try_from(size.height).context("image is too tall for jpeg")?;
+    let mut data: Vec<u8> = Vec::new();
+    jpeg_encoder::Encoder::new(&mut data, opts.jpeg_quality)
+        .encode(pixels, width, height, jpeg_encoder::ColorType::Rgba)
+        .context("could not encode jpeg")?;
+    Ok(data)
+}
+
+fn encode_webp(pixels: &[u8], size: Size) -> anyhow::Result<Vec<u8>> {
+    let mut data: Vec<u8> = Vec::new();
+    image_webp::WebPEncoder::new(&mut data)
+        .encode(
+            pixels,
+            size.width,
+            size.height,
+            image_webp::ColorType::Rgba8,
+        )
+        .context("could not encode webp")?;
+    Ok(data)
+}
+
+fn encode_qoi(pixels: &[u8], size: Size) -> anyhow::Result<Vec<u8>> {
+    qoi::encode_to_vec(pixels, size.width, size.height).context("could not encode qoi")
+}
+
+fn encode_ppm(pixels: &[u8], size: Size) -> Vec<u8> {
+    let mut data = Vec::with_capacity(32 + pixels.len() / 4 * 3);
+    write!(data, "P6\n{} {}\n255\n", size.width, size.height).unwrap();
+    for pixel in pixels.chunks_exact(4) {
+        data.extend_from_slice(&pixel[..3]);
+    }
+    data
+}
+
+fn encode_pam(pixels: &[u8], size: Size) -> Vec<u8> {
+    let mut data = Vec::with_capacity(80 + pixels.len());
+    write!(
+        data,
+        "P7\nWIDTH {}\nHEIGHT {}\nDEPTH 4\nMAXVAL 255\nTUPLTYPE RGB_ALPHA\nENDHDR\n",
+        size.width, size.height
+    )
+    .unwrap();
+    data.extend_from_slice(pixels);
+    data
+}
+
+const BMP_FILE_HEADER_SIZE: u32 = 14;
+const BMP_V4_HEADER_SIZE: u32 = 108;
+
+/// 32 bit top-down bitmap with v4 header, older headers have no alpha.
+fn encode_bmp(pixels: &[u8], size: Size) -> anyhow::Result<Vec<u8>> {
+    let width = i32::try_from(size.width).context("image is too wide for bmp")?;
+    let height = i32::try_from(size.height).context("image is too tall for bmp")?;
+    let offset = BMP_FILE_HEADER_SIZE + BMP_V4_HEADER_SIZE;
+    let image_size = u32::try_from(pixels.len()).context("image is too large for bmp")?;
+    let file_size = offset
+        .checked_add(image_size)
+        .context("image is too large for bmp")?;
+
+    let mut data = Vec::with_capacity(file_size as usize);
+    // file header
+    data.extend_from_slice(b"BM");
+    data.extend_from_slice(&file_size.to_le_bytes());
+    data.extend_from_slice(&0u32.to_le_bytes());
+    data.extend_from_slice(&offset.to_le_bytes());
+    // BITMAPV4HEADER
+    data.extend_from_slice(&BMP_V4_HEADER_SIZE.to_le_bytes());
+    data.extend_from_slice(&width.to_le_bytes());
+    // negative height means rows go top to bottom.
+    data.extend_from_slice(&(-height).to_le_bytes());
+    data.extend_from_slice(&1u16.to_le_bytes()); // planes
+    data.extend_from_slice(&32u16.to_le_bytes()); // bits per pixel
+    data.extend_from_slice(&3u32.to_le_bytes()); // BI_BITFIELDS
+    data.extend_from_slice(&image_size.to_le_bytes());
+    data.extend_from_slice(&2835i32.to_le_bytes()); // 72 dpi
+    data.extend_from_slice(&2835i32.to_le_bytes());
+    data.extend_from_slice(&0u32.to_le_bytes()); // colors used
+    data.extend_from_slice(&0u32.to_le_bytes()); // important colors
+    for mask in [0x00ff0000u32, 0x0000ff00, 0x000000ff, 0xff000000] {
+        data.extend_from_slice(&mask.to_le_bytes());
+    }
+    data.extend_from_slice(b"BGRs"); // LCS_sRGB
+    data.extend_from_slice(&[0; 36 + 12]); // endpoints and gamma, unused with srgb
+    debug_assert_eq!(data.len(), offset as usize);
+
+    for pixel in pixels.chunks_exact(4) {
+        data.extend_from_slice(&[pixel[2], pixel[1], pixel[0], pixel[3]]);
+    }
+    Ok(data)
+}
+
+pub fn encode(format: ImageFormat, image: &Image, opts: &EncodeOptions) -> anyhow::Result<Vec<u8>> {
+    let (pixels, size) = (image.pixels.as_slice(), image.size);
+    assert_eq!(pixels.len(), size.width as usize * size.height as usize * 4);
+    match format {
+        ImageFormat::Png => encode_png(image, opts),
+        ImageFormat::Jpeg => encode_jpeg(pixels, size, opts),
+        ImageFormat::Webp => encode_webp(pixels, size),
+        ImageFormat::Qoi => encode_qoi(pixels, size),
+        ImageFormat::Ppm => Ok(encode_ppm(pixels, size)),
+        ImageFormat::Pam => Ok(encode_pam(pixels, size)),
+        ImageFormat::Bmp => encode_bmp(pixels, size),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_format_from_path() {
+        assert_eq!(
+            ImageFormat::from_path(Path::new("/tmp/a.JPG")),
+            Some(ImageFormat::Jpeg)
+        );
+        assert_eq!(
+            ImageFormat::from_path(Path::new("a.webp")),
+            Some(ImageFormat::Webp)
+        );
+        assert_eq!(ImageFormat::from_path(Path::new("a")), None);
+        assert_eq!(ImageFormat::from_path(Path::new("a.txt")), None);
+    }
+
+    fn image(pixels: &[u8], width: u32, height: u32) -> Image {
+        Image {
+            pixels: pixels.to_vec(),
+            size: Size::new(width, height),
+            metadata: ImageMetadata::default(),
+        }
+    }
+
+    #[test]
+    fn test_encode_png_metadata() {
+        let mut image = image(&[1, 2, 3, 4], 1, 1);
+        image.metadata = ImageMetadata {
+            text: vec![
+                ("Software".to_string(), "bscreen".to_string()),
+                ("Source".to_string(), "монитор".to_string()),
+            ],
+            pixels_per_meter: Some((3780, 3780)),
+        };
+        let data = encode(ImageFormat::Png, &image, &EncodeOptions::default()).unwrap();
+
+        let reader = png::Decoder::new(data.as_slice()).read_info().unwrap();
+        let info = reader.info();
+        assert_eq!(info.uncompressed_latin1_text.len(), 1);
+        assert_eq!(info.uncompressed_latin1_text[0].text, "bscreen");
+        assert_eq!(info.utf8_text.len(), 1);
+        assert_eq!(info.utf8_text[0].get_text().unwrap(), "монитор");
+        let pixel_dims = info.pixel_dims.unwrap();
+        assert_eq!((pixel_dims.xppu, pixel_dims.yppu), (3780, 3780));
+        assert_eq!(pixel_dims.unit, png::Unit::Meter);
+    }
+
+    #[test]
+    fn test_encode_pnm() {
+        let image = image(&[1, 2, 3, 4, 5, 6, 7, 8], 2, 1);
+        let opts = EncodeOptions::default();
+        assert_eq!(
+            encode(ImageFormat::Ppm, &image, &opts).unwrap(),
+            b"P6\n2 1\n255\n\x01\x02\x03\x05\x06\x07"
+        );
+        let pam = encode(ImageFormat::Pam, &image, &opts).unwrap();
+        assert!(pam.starts_with(b"P7\nWIDTH 2\nHEIGHT 1\nDEPTH 4\n"));
+        assert!(pam.ends_with(b"ENDHDR\n\x01\x02\x03\x04\x05\x06\x07\x08"));
+    }
+
+    #[test]
+    fn test_encode_bmp() {
+        let data = encode(
+            ImageFormat::Bmp,
+            &image(&[1, 2, 3, 4], 1, 1),
+            &EncodeOptions::default(),
+        )
+        .unwrap();
+        assert_eq!(data.len(), 14 + 108 + 4);
+        assert_eq!(&data[..2], b"BM");
+        assert_eq!(&data[2..6], &(data.len() as u32).to_le_bytes());
+        assert_eq!(&data[22..26], &(-1i32).to_le_bytes());
+        assert_eq!(&data[122..], &[3, 2, 1, 4]);
+    }
+
+    #[test]
+    fn test_encode_qoi_roundtrip() {
+        let pixels: Vec<u8> = (0..4 * 3 * 4).map(|i| i as u8).collect();
+        let data = encode(
+            ImageFormat::Qoi,
+            &image(&pixels, 4, 3),
+            &EncodeOptions::default(),
+        )
+        .unwrap();
+        let (header, decoded) = qoi::decode_to_vec(&data).unwrap();
+        assert_eq!((header.width, header.height), (4, 3));
+        assert_eq!(decoded, pixels);
+    }
+}
@@ -40,6 +40,25 @@ impl FontProvider {
         Ok(self.fonts.insert(Font { inner: font, size }))
     }
 
+    /// returns a handle to the same font at a different size, creates it if necessary.
+    pub fn get_or_create_font_with_size(
+        &mut self,
+        font_handle: Handle<Font>,
+        size: f32,
+    ) -> Handle<Font> {
+        let file_hash = self.fonts.get(font_handle).inner.file_hash();
+        if let Some((handle, _)) = self
+            .fonts
+            .iter()
+            .find(|(_, it)| it.inner.file_hash() == file_hash && it.size == size)
+        {
+            return handle;
+        }
+
+        let inner = self.fonts.get(font_handle).inner.clone();
+        self.fonts.insert(Font { inner, size })
+    }
+
     pub fn get_font(&self, font_handle: Handle<Font>) -> &Font {
         &self.fonts.get(font_handle)
     }
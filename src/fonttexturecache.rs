@@ -36,9 +36,20 @@ pub struct FontTextureCache {
     pages: Vec<Page>,
     // TODO: rb tree or something might perform better?
     chars: HashMap<CharKey, CharValue>,
+    /// glyph coverage is sampled as alpha.
+    tinted: bool,
 }
 
 impl FontTextureCache {
+    /// glyphs are meant to be drawn with [`crate::gfx::DrawBuffer::push_rect_tinted`], they are
+    /// transparent outside of their coverage.
+    pub fn new_tinted() -> Self {
+        Self {
+            tinted: true,
+            ..Self::default()
+        }
+    }
+
     fn allocate_page(&mut self, ctx: &FontTextureCacheContext) -> usize {
         let texture_packer = TexturePacker::default();
         let texture = unsafe {
@@ -50,6 +61,9 @@ impl FontTextureCache {
                 None,
             )
         };
+        if self.tinted {
+            unsafe { texture.set_swizzle(gl::SWIZZLE_COVERAGE) };
+        }
 
         let page_index = self.pages.len();
         self.pages.push(Page {
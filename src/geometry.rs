@@ -0,0 +1,61 @@
+use anyhow::anyhow;
+
+use crate::gfx::{Rect, Vec2};
+
+/// parses slurp-compatible geometry, i.e. `X,Y WxH` in logical compositor coordinates.
+pub fn parse(geometry: &str) -> anyhow::Result<Rect> {
+    let invalid = || anyhow!("invalid geometry {geometry:?}, expected `X,Y WxH`");
+
+    let (position, size) = geometry.trim().split_once(' ').ok_or_else(invalid)?;
+    let (x, y) = position.split_once(',').ok_or_else(invalid)?;
+    let (width, height) = size.trim().split_once('x').ok_or_else(invalid)?;
+
+    let x: i32 = x.trim().parse().map_err(|_| invalid())?;
+    let y: i32 = y.trim().parse().map_err(|_| invalid())?;
+    let width: u32 = width.parse().map_err(|_| invalid())?;
+    let height: u32 = height.parse().map_err(|_| invalid())?;
+    if width == 0 || height == 0 {
+        return Err(anyhow!("geometry {geometry:?} is empty"));
+    }
+
+    let min = Vec2::new(x as f32, y as f32);
+    Ok(Rect::new(min, min + Vec2::new(width as f32, height as f32)))
+}
+
+/// formats the rect as slurp-compatible geometry, see `parse`.
+pub fn format(rect: &Rect) -> String {
+    format!(
+        "{},{} {}x{}",
+        rect.min.x.round(),
+        rect.min.y.round(),
+        rect.width().round(),
+        rect.height().round()
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse() {
+        let rect = parse("10,20 300x400\n").unwrap();
+        assert_eq!(rect.min, Vec2::new(10.0, 20.0));
+        assert_eq!(rect.max, Vec2::new(310.0, 420.0));
+
+        let rect = parse("-1920,0 1920x1080").unwrap();
+        assert_eq!(rect.min, Vec2::new(-1920.0, 0.0));
+        assert_eq!(rect.max, Vec2::new(0.0, 1080.0));
+        assert_eq!(format(&rect), "-1920,0 1920x1080");
+    }
+
+    #[test]
+    fn test_parse_invalid() {
+        assert!(parse("").is_err());
+        assert!(parse("10,20").is_err());
+        assert!(parse("10 20 300x400").is_err());
+        assert!(parse("10,20 300").is_err());
+        assert!(parse("10,20 0x400").is_err());
+        assert!(parse("10,20 -300x400").is_err());
+    }
+}
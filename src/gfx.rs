@@ -1,13 +1,30 @@
 // NOTE: TextureFormat is modeled after webgpu, see:
 // - https://github.com/webgpu-native/webgpu-headers/blob/449359147fae26c07efe4fece25013df396287db/webgpu.h
 // - https://www.w3.org/TR/webgpu/#texture-formats
+//
+// formats with x instead of a are the same in memory as their alpha counterparts, but x is not
+// alpha, x means that the bits are wasted and must not be sampled.
 pub enum TextureFormat {
     // Bgra8Unorm is compatible with VK_FORMAT_B8G8R8A8_UNORM, it is also
-    // compativle with DRM_FORMAT_XRGB8888 (x is not alpha, x means that the byte is
-    // wasted).
+    // compatible with DRM_FORMAT_ARGB8888.
     Bgra8Unorm,
+    // compatible with DRM_FORMAT_XRGB8888.
+    Bgrx8Unorm,
+    // compatible with DRM_FORMAT_ABGR8888.
     Rgba8Unorm,
+    // compatible with DRM_FORMAT_XBGR8888.
+    Rgbx8Unorm,
     R8Unorm,
+    // compatible with VK_FORMAT_R5G6B5_UNORM_PACK16 and DRM_FORMAT_RGB565.
+    R5g6b5Unorm,
+    // compatible with VK_FORMAT_A2B10G10R10_UNORM_PACK32 and DRM_FORMAT_ABGR2101010.
+    Rgb10a2Unorm,
+    // compatible with DRM_FORMAT_XBGR2101010.
+    Rgb10x2Unorm,
+    // compatible with VK_FORMAT_A2R10G10B10_UNORM_PACK32 and DRM_FORMAT_ARGB2101010.
+    Bgr10a2Unorm,
+    // compatible with DRM_FORMAT_XRGB2101010.
+    Bgr10x2Unorm,
 }
 
 #[derive(Debug, Default, Clone, Copy, PartialEq)]
@@ -284,6 +301,21 @@ impl Rect {
         ret
     }
 
+    pub fn union(&self, other: &Self) -> Self {
+        let mut ret = Self::default();
+        ret.min.x = self.min.x.min(other.min.x);
+        ret.min.y = self.min.y.min(other.min.y);
+        ret.max.x = self.max.x.max(other.max.x);
+        ret.max.y = self.max.y.max(other.max.y);
+        ret
+    }
+
+    pub fn intersects(&self, other: &Self) -> bool {
+        let x = self.min.x < other.max.x && other.min.x < self.max.x;
+        let y = self.min.y < other.max.y && other.min.y < self.max.y;
+        x && y
+    }
+
     pub fn translate(&self, delta: &Vec2) -> Self {
         Self::new(self.min + *delta, self.max + *delta)
     }
@@ -317,6 +349,42 @@ impl std::ops::Div<f32> for Rect {
     }
 }
 
+/// describes how normalized coordinates of a displayed image map into normalized coordinates of a
+/// texture that holds it. coordinates are flipped horizontally first, rotated counter-clockwise
+/// around the center second (as wl_output transforms are) and flipped vertically last.
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
+pub struct TextureTransform {
+    pub quarter_turns: u8,
+    pub flip_x: bool,
+    pub flip_y: bool,
+}
+
+impl TextureTransform {
+    pub fn apply(&self, coord: Vec2) -> Vec2 {
+        let mut centered = coord - Vec2::splat(0.5);
+        if self.flip_x {
+            centered.x = -centered.x;
+        }
+        // NOTE: y points down, thus this is counter-clockwise on screen.
+        for _ in 0..self.quarter_turns % 4 {
+            centered = Vec2::new(centered.y, -centered.x);
+        }
+        if self.flip_y {
+            centered.y = -centered.y;
+        }
+        centered + Vec2::splat(0.5)
+    }
+
+    /// size of the texture as it appears after the transform is applied.
+    pub fn apply_size(&self, size: Size) -> Size {
+        if self.quarter_turns % 2 == 1 {
+            Size::new(size.height, size.width)
+        } else {
+            size
+        }
+    }
+}
+
 #[derive(Debug, Clone, Copy)]
 pub enum RectFill {
     Texture { handle: u32, coords: Rect },
@@ -441,44 +509,168 @@ impl DrawBuffer {
         self.commit(None);
     }
 
-    pub fn push_rect_filled(&mut self, rect: Rect, fill: RectFill) {
+    pub fn push_triangle_filled(&mut self, a: Vec2, b: Vec2, c: Vec2, color: Rgba8) {
+        let idx = self.vertices.len() as u32;
+        for position in [a, b, c] {
+            self.push_vertex(Vertex {
+                position,
+                tex_coord: Vec2::ZERO,
+                color,
+            });
+        }
+        self.push_triangle(idx, idx + 1, idx + 2);
+        self.commit(None);
+    }
+
+    /// number of segments that makes a curve of the given radius look smooth.
+    fn segments_for_radius(radius: f32) -> u32 {
+        (radius.sqrt() * 4.0).clamp(8.0, 128.0) as u32
+    }
+
+    pub fn push_circle_filled(&mut self, center: Vec2, radius: f32, color: Rgba8) {
         let idx = self.vertices.len() as u32;
+        let segments = Self::segments_for_radius(radius);
 
-        let (color, tex_handle, tex_coords) = match fill {
-            RectFill::Color(color) => (color, None, None),
-            RectFill::Texture { handle, coords } => (Rgba8::WHITE, Some(handle), Some(coords)),
-        };
+        self.push_vertex(Vertex {
+            position: center,
+            tex_coord: Vec2::ZERO,
+            color,
+        });
+        for i in 0..segments {
+            let angle = i as f32 / segments as f32 * std::f32::consts::TAU;
+            self.push_vertex(Vertex {
+                position: center + Vec2::new(angle.cos(), angle.sin()) * radius,
+                tex_coord: Vec2::ZERO,
+                color,
+            });
+        }
+        for i in 0..segments {
+            self.push_triangle(idx, idx + 1 + i, idx + 1 + (i + 1) % segments);
+        }
+
+        self.commit(None);
+    }
+
+    /// segments are joined with round joins; ends are round too.
+    pub fn push_polyline(&mut self, points: &[Vec2], width: f32, color: Rgba8, closed: bool) {
+        for segment in points.windows(2) {
+            self.push_line(segment[0], segment[1], width, color);
+        }
+        if closed && points.len() > 2 {
+            self.push_line(points[points.len() - 1], points[0], width, color);
+        }
+        // NOTE: joins are only noticeable with wide lines.
+        if width > 2.0 {
+            for point in points.iter() {
+                self.push_circle_filled(*point, width * 0.5, color);
+            }
+        }
+    }
+
+    pub fn push_ellipse_outlined(&mut self, rect: Rect, width: f32, color: Rgba8) {
+        let center = (rect.min + rect.max) * 0.5;
+        let radii = Vec2::new(rect.width().abs(), rect.height().abs()) * 0.5;
+        let segments = Self::segments_for_radius(radii.x.max(radii.y));
+
+        let points: Vec<Vec2> = (0..segments)
+            .map(|i| {
+                let angle = i as f32 / segments as f32 * std::f32::consts::TAU;
+                center + Vec2::new(angle.cos(), angle.sin()) * radii
+            })
+            .collect();
+        self.push_polyline(&points, width, color, true);
+    }
+
+    /// the head is a filled triangle that ends at `to`, it is sized after the line width.
+    pub fn push_arrow(&mut self, from: Vec2, to: Vec2, width: f32, color: Rgba8) {
+        let dir = (to - from).normalize_or_zero();
+        let head_length = (width * 4.0).max(12.0).min((to - from).length());
+        let head_half_width = head_length * 0.5;
+
+        let head_base = to - dir * head_length;
+        // NOTE: shaft stops a bit inside of the head so that its square end does not stick out.
+        self.push_line(from, head_base + dir * (head_length * 0.25), width, color);
+        self.push_triangle_filled(
+            to,
+            head_base + dir.perp() * head_half_width,
+            head_base - dir.perp() * head_half_width,
+            color,
+        );
+    }
+
+    pub fn push_rect_filled(&mut self, rect: Rect, fill: RectFill) {
+        match fill {
+            RectFill::Color(color) => self.push_quad(rect, color, None, [
+                Vec2::new(0.0, 0.0),
+                Vec2::new(1.0, 0.0),
+                Vec2::new(1.0, 1.0),
+                Vec2::new(0.0, 1.0),
+            ]),
+            RectFill::Texture { handle, coords } => {
+                self.push_rect_textured(rect, handle, coords, TextureTransform::default())
+            }
+        }
+    }
+
+    /// same as [`Self::push_rect_filled`] with [`RectFill::Texture`], but allows texture
+    /// coordinates to be rotated and/or flipped.
+    pub fn push_rect_textured(
+        &mut self,
+        rect: Rect,
+        handle: u32,
+        coords: Rect,
+        transform: TextureTransform,
+    ) {
+        self.push_quad(rect, Rgba8::WHITE, Some(handle), [
+            transform.apply(coords.top_left()),
+            transform.apply(coords.top_right()),
+            transform.apply(coords.bottom_right()),
+            transform.apply(coords.bottom_left()),
+        ]);
+    }
+
+    /// draws a texture multiplied by color, e.g. glyphs of colored text.
+    pub fn push_rect_tinted(&mut self, rect: Rect, handle: u32, coords: Rect, color: Rgba8) {
+        self.push_quad(rect, color, Some(handle), [
+            coords.top_left(),
+            coords.top_right(),
+            coords.bottom_right(),
+            coords.bottom_left(),
+        ]);
+    }
+
+    /// tex coords are in top left, top right, bottom right, bottom left order.
+    fn push_quad(
+        &mut self,
+        rect: Rect,
+        color: Rgba8,
+        tex_handle: Option<u32>,
+        tex_coords: [Vec2; 4],
+    ) {
+        let idx = self.vertices.len() as u32;
 
         // top left
         self.push_vertex(Vertex {
             position: rect.top_left(),
-            tex_coord: tex_coords
-                .map(|tc| tc.top_left())
-                .unwrap_or(Vec2::new(0.0, 0.0)),
+            tex_coord: tex_coords[0],
             color,
         });
         // top right
         self.push_vertex(Vertex {
             position: rect.top_right(),
-            tex_coord: tex_coords
-                .map(|tc| tc.top_right())
-                .unwrap_or(Vec2::new(1.0, 0.0)),
+            tex_coord: tex_coords[1],
             color,
         });
         // bottom right
         self.push_vertex(Vertex {
             position: rect.bottom_right(),
-            tex_coord: tex_coords
-                .map(|tc| tc.bottom_right())
-                .unwrap_or(Vec2::new(1.0, 1.0)),
+            tex_coord: tex_coords[2],
             color,
         });
         // bottom left
         self.push_vertex(Vertex {
             position: rect.bottom_left(),
-            tex_coord: tex_coords
-                .map(|tc| tc.bottom_left())
-                .unwrap_or(Vec2::new(0.0, 1.0)),
+            tex_coord: tex_coords[3],
             color,
         });
 
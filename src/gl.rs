@@ -52,33 +52,76 @@ pub struct TextureFormatDescriptor {
     pub ty: sys::types::GLenum,
     // https://docs.vulkan.org/spec/latest/chapters/formats.html#texel-block-size
     pub block_size: sys::types::GLint,
+    /// gles lacks native equivalents for some formats, those are uploaded as a compatible format
+    /// and fixed up on sampling with TEXTURE_SWIZZLE_{R,G,B,A}.
+    pub swizzle: Option<[sys::types::GLenum; 4]>,
 }
 
+const SWIZZLE_IGNORE_ALPHA: [sys::types::GLenum; 4] = [sys::RED, sys::GREEN, sys::BLUE, sys::ONE];
+const SWIZZLE_SWAP_RED_BLUE: [sys::types::GLenum; 4] =
+    [sys::BLUE, sys::GREEN, sys::RED, sys::ALPHA];
+const SWIZZLE_SWAP_RED_BLUE_IGNORE_ALPHA: [sys::types::GLenum; 4] =
+    [sys::BLUE, sys::GREEN, sys::RED, sys::ONE];
+/// single channel textures may hold coverage (e.g. glyphs), moving it into alpha allows to tint
+/// them with vertex color.
+pub const SWIZZLE_COVERAGE: [sys::types::GLenum; 4] = [sys::ONE, sys::ONE, sys::ONE, sys::RED];
+
 fn describe_texture_format(format: gfx::TextureFormat) -> TextureFormatDescriptor {
     use gfx::TextureFormat::*;
     match format {
         // https://gitlab.freedesktop.org/wlroots/wlroots/-/blob/3fdbfb0be82224d472ad6de3a91813064f4cd4b2/render/gles2/pixel_format.c
-        Bgra8Unorm => TextureFormatDescriptor {
+        Bgra8Unorm | Bgrx8Unorm => TextureFormatDescriptor {
             internal_format: sys::BGRA_EXT as _,
             format: sys::BGRA_EXT,
             ty: sys::UNSIGNED_BYTE,
             block_size: 4,
+            swizzle: matches!(format, Bgrx8Unorm).then_some(SWIZZLE_IGNORE_ALPHA),
         },
-        Rgba8Unorm => TextureFormatDescriptor {
+        Rgba8Unorm | Rgbx8Unorm => TextureFormatDescriptor {
             internal_format: sys::RGBA as _,
             format: sys::RGBA,
             ty: sys::UNSIGNED_BYTE,
             block_size: 4,
+            swizzle: matches!(format, Rgbx8Unorm).then_some(SWIZZLE_IGNORE_ALPHA),
         },
         R8Unorm => TextureFormatDescriptor {
             internal_format: sys::LUMINANCE as _,
             format: sys::LUMINANCE,
             ty: sys::UNSIGNED_BYTE,
             block_size: 1,
+            swizzle: None,
+        },
+        R5g6b5Unorm => TextureFormatDescriptor {
+            internal_format: sys::RGB as _,
+            format: sys::RGB,
+            ty: sys::UNSIGNED_SHORT_5_6_5,
+            block_size: 2,
+            swizzle: None,
+        },
+        // NOTE: UNSIGNED_INT_2_10_10_10_REV stores red in the least significant bits, there's no
+        // native format with blue in the least significant bits.
+        Rgb10a2Unorm | Rgb10x2Unorm | Bgr10a2Unorm | Bgr10x2Unorm => TextureFormatDescriptor {
+            internal_format: sys::RGB10_A2 as _,
+            format: sys::RGBA,
+            ty: sys::UNSIGNED_INT_2_10_10_10_REV,
+            block_size: 4,
+            swizzle: match format {
+                Rgb10x2Unorm => Some(SWIZZLE_IGNORE_ALPHA),
+                Bgr10a2Unorm => Some(SWIZZLE_SWAP_RED_BLUE),
+                Bgr10x2Unorm => Some(SWIZZLE_SWAP_RED_BLUE_IGNORE_ALPHA),
+                _ => None,
+            },
         },
     }
 }
 
+unsafe fn set_bound_texture_swizzle(gl_lib: &Lib, swizzle: [sys::types::GLenum; 4]) {
+    gl_lib.TexParameteri(sys::TEXTURE_2D, sys::TEXTURE_SWIZZLE_R, swizzle[0] as _);
+    gl_lib.TexParameteri(sys::TEXTURE_2D, sys::TEXTURE_SWIZZLE_G, swizzle[1] as _);
+    gl_lib.TexParameteri(sys::TEXTURE_2D, sys::TEXTURE_SWIZZLE_B, swizzle[2] as _);
+    gl_lib.TexParameteri(sys::TEXTURE_2D, sys::TEXTURE_SWIZZLE_A, swizzle[3] as _);
+}
+
 pub struct Texture2D {
     gl_lib: &'static Lib,
     pub format_desc: TextureFormatDescriptor,
@@ -118,12 +161,22 @@ impl Texture2D {
             pixels.map(|pixels| pixels.as_ptr()).unwrap_or(null()) as _,
         );
 
+        if let Some(swizzle) = format_desc.swizzle {
+            set_bound_texture_swizzle(gl_lib, swizzle);
+        }
+
         Self {
             gl_lib,
             format_desc,
             handle: texture,
         }
     }
+
+    /// overrides swizzle of the format, e.g. with [`SWIZZLE_COVERAGE`].
+    pub unsafe fn set_swizzle(&self, swizzle: [sys::types::GLenum; 4]) {
+        self.gl_lib.BindTexture(sys::TEXTURE_2D, self.handle);
+        set_bound_texture_swizzle(self.gl_lib, swizzle);
+    }
 }
 
 impl Drop for Texture2D {
@@ -250,6 +303,52 @@ impl Drop for Buffer {
     }
 }
 
+pub struct Framebuffer {
+    gl_lib: &'static Lib,
+    pub texture: Texture2D,
+    pub handle: sys::types::GLuint,
+}
+
+impl Framebuffer {
+    /// creates framebuffer with rgba8 color attachment and binds it.
+    pub unsafe fn new(gl_lib: &'static Lib, width: u32, height: u32) -> anyhow::Result<Self> {
+        let texture = Texture2D::new(gl_lib, width, height, gfx::TextureFormat::Rgba8Unorm, None);
+
+        let mut handle = 0;
+        gl_lib.GenFramebuffers(1, &mut handle);
+        // NOTE: framebuffer is constructed before status check so that it'll be deleted on error.
+        let framebuffer = Self {
+            gl_lib,
+            texture,
+            handle,
+        };
+
+        gl_lib.BindFramebuffer(sys::FRAMEBUFFER, handle);
+        gl_lib.FramebufferTexture2D(
+            sys::FRAMEBUFFER,
+            sys::COLOR_ATTACHMENT0,
+            sys::TEXTURE_2D,
+            framebuffer.texture.handle,
+            0,
+        );
+        let status = gl_lib.CheckFramebufferStatus(sys::FRAMEBUFFER);
+        if status != sys::FRAMEBUFFER_COMPLETE {
+            gl_lib.BindFramebuffer(sys::FRAMEBUFFER, 0);
+            return Err(anyhow!("framebuffer is incomplete (status 0x{status:x})"));
+        }
+
+        Ok(framebuffer)
+    }
+}
+
+impl Drop for Framebuffer {
+    fn drop(&mut self) {
+        unsafe {
+            self.gl_lib.DeleteFramebuffers(1, &self.handle);
+        }
+    }
+}
+
 // TODO: it might make senst to require Rect and Size to be generic over u32 instead of f32 here.
 pub unsafe fn read_pixels(gl_lib: &'static Lib, read_rect: Rect, view_size: Size) -> Vec<u8> {
     // TODO: maybe unhardcode this and instead rely on TextureFormat
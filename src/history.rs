@@ -0,0 +1,230 @@
+use std::collections::VecDeque;
+
+use crate::{
+    annotate::{Annotate, Shape, ShapeKind},
+    crop::Crop,
+    gfx::Rect,
+};
+
+/// oldest commands are forgotten past this.
+const MAX_COMMANDS: usize = 100;
+/// oldest commands are forgotten once shapes that they refer to take more than this, see
+/// [`shape_bytes`].
+const MAX_SHAPE_BYTES: usize = 4 * 1024 * 1024;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Command {
+    /// selection before and after a change.
+    Crop {
+        before: Option<Rect>,
+        after: Option<Rect>,
+    },
+    /// a shape that was added on top of others, the shape itself stays in annotate.
+    AddShape { bytes: usize },
+}
+
+impl Command {
+    fn shape_bytes(&self) -> usize {
+        match self {
+            Self::Crop { .. } => 0,
+            Self::AddShape { bytes } => *bytes,
+        }
+    }
+}
+
+/// approximate size of the shape, including what it holds on the heap (e.g. pen points).
+fn shape_bytes(shape: &Shape) -> usize {
+    let heap_bytes = match &shape.kind {
+        ShapeKind::Pen(points) => std::mem::size_of_val(points.as_slice()),
+        ShapeKind::Text { text, .. } => text.len(),
+        _ => 0,
+    };
+    std::mem::size_of::<Shape>() + heap_bytes
+}
+
+// NOTE: changes are recorded by comparing crop and annotations with the state that was recorded
+// last, thus they do not need to know about history.
+#[derive(Debug, Default)]
+pub struct History {
+    undo: VecDeque<Command>,
+    /// undone commands along with shapes that they took away.
+    redo: Vec<(Command, Option<Shape>)>,
+    crop_rect: Option<Rect>,
+    shape_count: usize,
+    /// of shapes that undo and redo commands refer to. undo and redo move commands between each
+    /// other, thus this only grows when commands are pushed.
+    shape_bytes: usize,
+}
+
+impl History {
+    fn push(&mut self, command: Command) {
+        for (command, _) in self.redo.drain(..) {
+            self.shape_bytes -= command.shape_bytes();
+        }
+        self.shape_bytes += command.shape_bytes();
+        self.undo.push_back(command);
+        // NOTE: the newest command is kept regardless of its size, otherwise e.g. a long pen
+        // stroke could not be undone right after it was drawn.
+        while self.undo.len() > MAX_COMMANDS
+            || (self.shape_bytes > MAX_SHAPE_BYTES && self.undo.len() > 1)
+        {
+            let command = self.undo.pop_front().unwrap();
+            self.shape_bytes -= command.shape_bytes();
+        }
+    }
+
+    /// records changes that were made since the last call; changes that are still in progress
+    /// (e.g. dragging of a crop handle) are recorded once finished.
+    pub fn record(&mut self, crop: &Crop, annotate: &Annotate) {
+        if !crop.is_dragging() && crop.crop_rect != self.crop_rect {
+            self.push(Command::Crop {
+                before: self.crop_rect,
+                after: crop.crop_rect,
+            });
+            self.crop_rect = crop.crop_rect;
+        }
+
+        while self.shape_count < annotate.shapes.len() {
+            let bytes = shape_bytes(&annotate.shapes[self.shape_count]);
+            self.push(Command::AddShape { bytes });
+            self.shape_count += 1;
+        }
+    }
+
+    pub fn undo(&mut self, crop: &mut Crop, annotate: &mut Annotate) {
+        let Some(command) = self.undo.pop_back() else {
+            return;
+        };
+        let shape = match command {
+            Command::Crop { before, .. } => {
+                crop.crop_rect = before;
+                self.crop_rect = before;
+                None
+            }
+            Command::AddShape { .. } => {
+                self.shape_count -= 1;
+                annotate.shapes.pop()
+            }
+        };
+        self.redo.push((command, shape));
+    }
+
+    pub fn redo(&mut self, crop: &mut Crop, annotate: &mut Annotate) {
+        let Some((command, shape)) = self.redo.pop() else {
+            return;
+        };
+        match command {
+            Command::Crop { after, .. } => {
+                crop.crop_rect = after;
+                self.crop_rect = after;
+            }
+            Command::AddShape { .. } => {
+                annotate.shapes.extend(shape);
+                self.shape_count += 1;
+            }
+        }
+        self.undo.push_back(command);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::gfx::{Rgba8, Vec2};
+
+    #[test]
+    fn test_undo_redo() {
+        let mut history = History::default();
+        let mut crop = Crop::default();
+        let mut annotate = Annotate::default();
+
+        let rect = Rect::new(Vec2::ZERO, Vec2::new(10.0, 10.0));
+        crop.crop_rect = Some(rect);
+        history.record(&crop, &annotate);
+        annotate.shapes.push(Shape {
+            kind: ShapeKind::Pen(vec![Vec2::ZERO]),
+            color: Rgba8::WHITE,
+            width: 2.0,
+        });
+        history.record(&crop, &annotate);
+        // NOTE: a stray click throws the selection away.
+        crop.crop_rect = None;
+        history.record(&crop, &annotate);
+
+        history.undo(&mut crop, &mut annotate);
+        assert_eq!(crop.crop_rect, Some(rect));
+        assert_eq!(annotate.shapes.len(), 1);
+        history.undo(&mut crop, &mut annotate);
+        assert_eq!(annotate.shapes.len(), 0);
+
+        history.redo(&mut crop, &mut annotate);
+        assert_eq!(annotate.shapes.len(), 1);
+        assert_eq!(annotate.shapes[0].kind, ShapeKind::Pen(vec![Vec2::ZERO]));
+        // NOTE: undone changes are not recorded again.
+        history.record(&crop, &annotate);
+        history.redo(&mut crop, &mut annotate);
+        assert_eq!(crop.crop_rect, None);
+
+        // NOTE: a new change drops what could have been redone.
+        history.undo(&mut crop, &mut annotate);
+        crop.crop_rect = Some(Rect::new(Vec2::ZERO, Vec2::splat(5.0)));
+        history.record(&crop, &annotate);
+        history.redo(&mut crop, &mut annotate);
+        assert_eq!(crop.crop_rect.unwrap().size(), Vec2::splat(5.0));
+    }
+
+    #[test]
+    fn test_bounded() {
+        let mut history = History::default();
+        let mut crop = Crop::default();
+        let mut annotate = Annotate::default();
+
+        for i in 0..MAX_COMMANDS * 2 {
+            crop.crop_rect = Some(Rect::new(Vec2::ZERO, Vec2::splat(i as f32 + 1.0)));
+            history.record(&crop, &annotate);
+        }
+        assert_eq!(history.undo.len(), MAX_COMMANDS);
+
+        for _ in 0..MAX_COMMANDS * 2 {
+            history.undo(&mut crop, &mut annotate);
+        }
+        let oldest = Rect::new(Vec2::ZERO, Vec2::splat(MAX_COMMANDS as f32));
+        assert_eq!(crop.crop_rect, Some(oldest));
+    }
+
+    #[test]
+    fn test_bounded_by_shape_bytes() {
+        let mut history = History::default();
+        let mut crop = Crop::default();
+        let mut annotate = Annotate::default();
+
+        let stroke = Shape {
+            kind: ShapeKind::Pen(vec![Vec2::ZERO; MAX_SHAPE_BYTES / 3 / size_of::<Vec2>()]),
+            color: Rgba8::WHITE,
+            width: 2.0,
+        };
+        for _ in 0..4 {
+            annotate.shapes.push(stroke.clone());
+            history.record(&crop, &annotate);
+        }
+        assert_eq!(history.undo.len(), 2);
+        assert!(history.shape_bytes <= MAX_SHAPE_BYTES);
+
+        // NOTE: undone shapes are held by redo and still count.
+        for _ in 0..4 {
+            history.undo(&mut crop, &mut annotate);
+        }
+        assert_eq!(annotate.shapes.len(), 2);
+        assert_eq!(history.shape_bytes, shape_bytes(&stroke) * 2);
+
+        // NOTE: a shape larger than the budget can still be undone.
+        annotate.shapes.push(Shape {
+            kind: ShapeKind::Pen(vec![Vec2::ZERO; MAX_SHAPE_BYTES / size_of::<Vec2>() + 1]),
+            ..stroke
+        });
+        history.record(&crop, &annotate);
+        assert_eq!(history.shape_bytes, shape_bytes(&annotate.shapes[2]));
+        history.undo(&mut crop, &mut annotate);
+        assert_eq!(annotate.shapes.len(), 2);
+    }
+}
@@ -5,16 +5,52 @@ use crate::gfx::Vec2;
 // https://github.com/torvalds/linux/blob/231825b2e1ff6ba799c5eaf396d3ab2354e37c6b/include/uapi/linux/input-event-codes.h#L76
 
 const KEY_ESC: u32 = 1;
+const KEY_BACKSPACE: u32 = 14;
+const KEY_ENTER: u32 = 28;
+const KEY_W: u32 = 17;
+const KEY_R: u32 = 19;
+const KEY_T: u32 = 20;
+const KEY_I: u32 = 23;
+const KEY_O: u32 = 24;
+const KEY_P: u32 = 25;
 const KEY_A: u32 = 30;
+const KEY_S: u32 = 31;
+const KEY_D: u32 = 32;
+const KEY_F: u32 = 33;
+const KEY_Z: u32 = 44;
 const KEY_C: u32 = 46;
+const KEY_B: u32 = 48;
+const KEY_HOME: u32 = 102;
+const KEY_LEFT: u32 = 105;
+const KEY_RIGHT: u32 = 106;
+const KEY_END: u32 = 107;
+const KEY_DELETE: u32 = 111;
 
 const BTN_LEFT: u32 = 0x110;
 
 #[derive(Debug, Clone, Copy, PartialEq)]
 pub enum Scancode {
     Esc,
+    Backspace,
+    Enter,
+    W,
+    R,
+    T,
+    I,
+    O,
+    P,
     A,
+    S,
+    D,
+    F,
+    Z,
     C,
+    B,
+    Home,
+    Left,
+    Right,
+    End,
+    Delete,
     Unidentified(u32),
 }
 
@@ -22,8 +58,26 @@ impl Scancode {
     pub fn from_int(int: u32) -> Scancode {
         match int {
             KEY_ESC => Self::Esc,
+            KEY_BACKSPACE => Self::Backspace,
+            KEY_ENTER => Self::Enter,
+            KEY_W => Self::W,
+            KEY_R => Self::R,
+            KEY_T => Self::T,
+            KEY_I => Self::I,
+            KEY_O => Self::O,
+            KEY_P => Self::P,
             KEY_A => Self::A,
+            KEY_S => Self::S,
+            KEY_D => Self::D,
+            KEY_F => Self::F,
+            KEY_Z => Self::Z,
             KEY_C => Self::C,
+            KEY_B => Self::B,
+            KEY_HOME => Self::Home,
+            KEY_LEFT => Self::Left,
+            KEY_RIGHT => Self::Right,
+            KEY_END => Self::End,
+            KEY_DELETE => Self::Delete,
             _ => Self::Unidentified(int),
         }
     }
@@ -33,12 +87,15 @@ impl Scancode {
 #[derive(Debug, Clone)]
 pub struct KeyboardMods {
     pub ctrl: bool,
+    pub shift: bool,
 }
 
 #[derive(Debug, PartialEq)]
 pub enum KeyboardEventKind {
     Press { scancode: Scancode },
     Release { scancode: Scancode },
+    /// a held key that is repeated, only meaningful for text editing.
+    Repeat { scancode: Scancode },
     Enter,
     Leave,
 }
@@ -48,6 +105,8 @@ pub struct KeyboardEvent {
     pub kind: KeyboardEventKind,
     pub surface_id: u64,
     pub mods: KeyboardMods,
+    /// utf-8 text produced by a key press, if any.
+    pub text: Option<String>,
 }
 
 #[derive(Debug, Clone, Copy, PartialEq)]
@@ -0,0 +1,115 @@
+use crate::{
+    fontprovider::{Font, FontProvider},
+    fonttexturecache::{FontTextureCache, FontTextureCacheContext},
+    genvec::Handle,
+    gfx::{DrawBuffer, Rect, RectFill, Rgba8, Vec2},
+    gl,
+};
+
+pub mod theme {
+    use crate::gfx::Rgba8;
+
+    pub const PADDING: f32 = 8.0;
+    pub const SWATCH_SIZE: f32 = 24.0;
+    pub const SWATCH_OUTLINE: Rgba8 = Rgba8::new(255, 255, 255, 255);
+    pub const SWATCH_OUTLINE_WIDTH: f32 = 1.0;
+    pub const BG: Rgba8 = Rgba8::new(0, 0, 0, 192);
+}
+
+pub struct LabelDrawData<'a> {
+    pub font_provider: &'a FontProvider,
+    pub font_handle: Handle<Font>,
+    pub font_texture_cache: &'a mut FontTextureCache,
+    pub gl_lib: &'static gl::Lib,
+}
+
+/// an optional color swatch followed by a line of text on a dark background.
+pub struct Label {
+    text_layout: fontdue::layout::Layout,
+    text_size: Vec2,
+    swatch: Option<Rgba8>,
+}
+
+impl Default for Label {
+    fn default() -> Self {
+        Self {
+            text_layout: fontdue::layout::Layout::new(
+                fontdue::layout::CoordinateSystem::PositiveYDown,
+            ),
+            text_size: Vec2::ZERO,
+            swatch: None,
+        }
+    }
+}
+
+impl Label {
+    /// lays out the text and returns size of the whole label.
+    pub fn layout(&mut self, text: &str, swatch: Option<Rgba8>, data: &LabelDrawData) -> Vec2 {
+        self.text_layout
+            .reset(&fontdue::layout::LayoutSettings::default());
+        let font = data.font_provider.get_font(data.font_handle);
+        self.text_layout.append(
+            &[&font.inner],
+            &fontdue::layout::TextStyle::new(text, font.size, 0),
+        );
+        let width = self
+            .text_layout
+            .glyphs()
+            .iter()
+            .map(|glyph| glyph.x + glyph.width as f32)
+            .fold(0.0, f32::max);
+        self.text_size = Vec2::new(width, self.text_layout.height());
+        self.swatch = swatch;
+
+        if self.swatch.is_none() {
+            return self.text_size + Vec2::splat(theme::PADDING * 2.0);
+        }
+        Vec2::new(
+            theme::SWATCH_SIZE + self.text_size.x + theme::PADDING * 3.0,
+            theme::SWATCH_SIZE.max(self.text_size.y) + theme::PADDING * 2.0,
+        )
+    }
+
+    /// draws the text and the swatch that were laid out last.
+    pub fn draw(&self, draw_buffer: &mut DrawBuffer, rect: Rect, data: &mut LabelDrawData) {
+        draw_buffer.push_rect_filled(rect, RectFill::Color(theme::BG));
+
+        let mut text_x = rect.min.x + theme::PADDING;
+        if let Some(swatch) = self.swatch {
+            let swatch_min = Vec2::new(
+                rect.min.x + theme::PADDING,
+                rect.min.y + (rect.height() - theme::SWATCH_SIZE) / 2.0,
+            );
+            let swatch_rect = Rect::new(swatch_min, swatch_min + Vec2::splat(theme::SWATCH_SIZE));
+            draw_buffer.push_rect_filled(swatch_rect, RectFill::Color(Rgba8 { a: 255, ..swatch }));
+            draw_buffer.push_rect_outlined(
+                swatch_rect,
+                theme::SWATCH_OUTLINE_WIDTH,
+                theme::SWATCH_OUTLINE,
+            );
+            text_x = swatch_rect.max.x + theme::PADDING;
+        }
+
+        let text_min = Vec2::new(
+            text_x,
+            rect.min.y + (rect.height() - self.text_size.y) / 2.0,
+        );
+        for glyph in self.text_layout.glyphs().iter() {
+            let (tex, x1, y1, x2, y2) = data.font_texture_cache.get_texture_for_char(
+                data.font_handle,
+                glyph.parent,
+                &FontTextureCacheContext {
+                    font_provider: data.font_provider,
+                    gl_lib: data.gl_lib,
+                },
+            );
+
+            let min = text_min + Vec2::new(glyph.x, glyph.y);
+            let size = Vec2::new(glyph.width as f32, glyph.height as f32);
+            draw_buffer.push_rect_filled(Rect::new(min, min + size), RectFill::Texture {
+                handle: tex.handle,
+                coords: Rect::new(Vec2::new(x1, y1), Vec2::new(x2, y2)),
+            });
+        }
+    }
+}
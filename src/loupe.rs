@@ -0,0 +1,209 @@
+use crate::{
+    fontprovider::{Font, FontProvider},
+    fonttexturecache::FontTextureCache,
+    genvec::Handle,
+    gfx::{DrawBuffer, Rect, RectFill, Rgba8, Size, TextureTransform, Vec2},
+    gl,
+    label::{self, Label, LabelDrawData},
+    picker::ColorFormat,
+};
+
+pub mod theme {
+    use crate::gfx::Rgba8;
+
+    /// distance between the pointer and the loupe.
+    pub const POINTER_OFFSET: f32 = 16.0;
+    /// number of physical pixels across, odd so that the pixel under the pointer is in the
+    /// center.
+    pub const PIXELS: i32 = 15;
+    /// logical size of a magnified pixel.
+    pub const ZOOM: f32 = 8.0;
+    /// shown where the loupe goes past the screen.
+    pub const BG: Rgba8 = Rgba8::new(0, 0, 0, 255);
+    pub const GRID: Rgba8 = Rgba8::new(0, 0, 0, 48);
+    pub const GRID_WIDTH: f32 = 1.0;
+    pub const CROSSHAIR: Rgba8 = Rgba8::new(255, 255, 255, 48);
+    pub const CENTER_OUTLINE: Rgba8 = Rgba8::new(255, 255, 255, 255);
+    pub const OUTLINE: Rgba8 = Rgba8::new(255, 255, 255, 255);
+    pub const OUTLINE_WIDTH: f32 = 1.0;
+}
+
+pub struct LoupeDrawData<'a> {
+    pub texture_handle: u32,
+    pub texture_transform: TextureTransform,
+    /// physical size of the screen, that is size of the texture with the transform applied.
+    pub texture_size: Size,
+    pub font_provider: &'a FontProvider,
+    pub font_handle: Handle<Font>,
+    pub font_texture_cache: &'a mut FontTextureCache,
+    pub gl_lib: &'static gl::Lib,
+}
+
+/// physical pixel under the logical position within a view.
+fn pixel_at(position: Vec2, view_size: Vec2, texture_size: Size) -> (i32, i32) {
+    let scale = texture_size.as_vec2() / view_size;
+    let x = (position.x * scale.x).floor() as i32;
+    let y = (position.y * scale.y).floor() as i32;
+    (
+        x.clamp(0, texture_size.width as i32 - 1),
+        y.clamp(0, texture_size.height as i32 - 1),
+    )
+}
+
+/// puts something of size next to the pointer, on the other side of it near view edges.
+fn place(pointer: Vec2, size: Vec2, view_size: Vec2) -> Vec2 {
+    let mut min = pointer + Vec2::splat(theme::POINTER_OFFSET);
+    if min.x + size.x > view_size.x {
+        min.x = pointer.x - theme::POINTER_OFFSET - size.x;
+    }
+    if min.y + size.y > view_size.y {
+        min.y = pointer.y - theme::POINTER_OFFSET - size.y;
+    }
+    min
+}
+
+/// magnified pixels of the screen around the pointer with their physical coordinates.
+#[derive(Default)]
+pub struct Loupe {
+    label: Label,
+}
+
+impl Loupe {
+    /// position is in global compositor coordinates, view rect is the rect of the screen.
+    /// color, if any, is shown along with coordinates.
+    pub fn draw(
+        &mut self,
+        draw_buffer: &mut DrawBuffer,
+        view_rect: Rect,
+        position: Vec2,
+        color: Option<Rgba8>,
+        data: &mut LoupeDrawData,
+    ) {
+        if !view_rect.contains(&position) {
+            return;
+        }
+        let view_size = view_rect.size();
+        let pointer = position - view_rect.min;
+        let (x, y) = pixel_at(pointer, view_size, data.texture_size);
+
+        let text = match color {
+            Some(color) => format!(
+                "{}  {}  {x}, {y}",
+                ColorFormat::Hex.format(color),
+                ColorFormat::Rgb.format(color)
+            ),
+            None => format!("{x}, {y}"),
+        };
+        let mut label_draw_data = LabelDrawData {
+            font_provider: data.font_provider,
+            font_handle: data.font_handle,
+            font_texture_cache: data.font_texture_cache,
+            gl_lib: data.gl_lib,
+        };
+        let label_size = self.label.layout(&text, color, &label_draw_data);
+
+        let loupe_size = theme::PIXELS as f32 * theme::ZOOM;
+        let size = Vec2::new(
+            loupe_size.max(label_size.x),
+            loupe_size + label::theme::PADDING + label_size.y,
+        );
+        let min = place(pointer, size, view_size);
+        let rect = Rect::new(min, min + Vec2::splat(loupe_size));
+
+        // NOTE: the sampled rect is clipped to the texture because pixels past its edges would
+        // be wrapped around.
+        let half = theme::PIXELS / 2;
+        let first = (x - half, y - half);
+        let x0 = first.0.max(0);
+        let y0 = first.1.max(0);
+        let x1 = (x + half + 1).min(data.texture_size.width as i32);
+        let y1 = (y + half + 1).min(data.texture_size.height as i32);
+        let cell_min = |x: i32, y: i32| {
+            rect.min + Vec2::new((x - first.0) as f32, (y - first.1) as f32) * theme::ZOOM
+        };
+        let texture_size = data.texture_size.as_vec2();
+        let coords = Rect::new(
+            Vec2::new(x0 as f32, y0 as f32) / texture_size,
+            Vec2::new(x1 as f32, y1 as f32) / texture_size,
+        );
+        draw_buffer.push_rect_filled(rect, RectFill::Color(theme::BG));
+        draw_buffer.push_rect_textured(
+            Rect::new(cell_min(x0, y0), cell_min(x1, y1)),
+            data.texture_handle,
+            coords,
+            data.texture_transform,
+        );
+
+        for i in 1..theme::PIXELS {
+            let offset = i as f32 * theme::ZOOM;
+            let vertical = Rect::new(
+                Vec2::new(rect.min.x + offset, rect.min.y),
+                Vec2::new(rect.min.x + offset + theme::GRID_WIDTH, rect.max.y),
+            );
+            draw_buffer.push_rect_filled(vertical, RectFill::Color(theme::GRID));
+            let horizontal = Rect::new(
+                Vec2::new(rect.min.x, rect.min.y + offset),
+                Vec2::new(rect.max.x, rect.min.y + offset + theme::GRID_WIDTH),
+            );
+            draw_buffer.push_rect_filled(horizontal, RectFill::Color(theme::GRID));
+        }
+
+        let center = Rect::new(cell_min(x, y), cell_min(x + 1, y + 1));
+        let row = Rect::new(
+            Vec2::new(rect.min.x, center.min.y),
+            Vec2::new(rect.max.x, center.max.y),
+        );
+        let column = Rect::new(
+            Vec2::new(center.min.x, rect.min.y),
+            Vec2::new(center.max.x, rect.max.y),
+        );
+        draw_buffer.push_rect_filled(row, RectFill::Color(theme::CROSSHAIR));
+        draw_buffer.push_rect_filled(column, RectFill::Color(theme::CROSSHAIR));
+        draw_buffer.push_rect_outlined(center, theme::OUTLINE_WIDTH, theme::CENTER_OUTLINE);
+        draw_buffer.push_rect_outlined(rect, theme::OUTLINE_WIDTH, theme::OUTLINE);
+
+        let label_min = Vec2::new(min.x, rect.max.y + label::theme::PADDING);
+        self.label.draw(
+            draw_buffer,
+            Rect::new(label_min, label_min + label_size),
+            &mut label_draw_data,
+        );
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_pixel_at() {
+        let view_size = Vec2::new(100.0, 50.0);
+        let texture_size = Size::new(150, 75);
+        assert_eq!(pixel_at(Vec2::ZERO, view_size, texture_size), (0, 0));
+        assert_eq!(
+            pixel_at(Vec2::new(10.5, 3.0), view_size, texture_size),
+            (15, 4)
+        );
+        // NOTE: the far edge of the view is still within the texture.
+        assert_eq!(pixel_at(view_size, view_size, texture_size), (149, 74));
+    }
+
+    #[test]
+    fn test_place() {
+        let view_size = Vec2::new(1000.0, 500.0);
+        let size = Vec2::new(100.0, 100.0);
+        let offset = theme::POINTER_OFFSET;
+        assert_eq!(
+            place(Vec2::new(10.0, 10.0), size, view_size),
+            Vec2::splat(10.0 + offset)
+        );
+        assert_eq!(
+            place(Vec2::new(950.0, 10.0), size, view_size),
+            Vec2::new(950.0 - offset - 100.0, 10.0 + offset)
+        );
+        assert_eq!(
+            place(Vec2::new(950.0, 450.0), size, view_size),
+            Vec2::new(950.0 - offset - 100.0, 450.0 - offset - 100.0)
+        );
+    }
+}
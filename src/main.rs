@@ -1,39 +1,66 @@
+mod annotate;
+mod config;
 mod crop;
 mod dynlib;
 mod egl;
+mod encoder;
 mod fontprovider;
 mod fonttexturecache;
 mod genvec;
+mod geometry;
 mod gfx;
 mod gl;
+mod history;
 mod input;
+mod label;
+mod loupe;
+mod metadata;
 mod ntree;
+mod picker;
+mod redact;
 mod renderer;
+mod resample;
+mod stitch;
+mod template;
 mod texturepacker;
 mod wayland;
 mod wayland_clipboard;
 mod wayland_cursor;
 mod wayland_egl;
 mod wayland_input;
+mod wayland_output;
 mod wayland_overlay;
 mod wayland_screencopy;
 mod welcome;
 mod xkbcommon;
 
 use std::{
-    ffi::{CStr, c_char, c_void},
+    ffi::{CStr, c_char, c_int, c_void},
+    fs,
+    io::Write as _,
+    path::PathBuf,
     ptr::{NonNull, null_mut},
     rc::Rc,
+    time::{Duration, SystemTime},
 };
 
+use annotate::Annotate;
 use anyhow::{Context as _, anyhow};
+use config::Config;
 use crop::{Crop, CropUpdateData};
+use encoder::ImageFormat;
 use fontprovider::{Font, FontProvider};
 use fonttexturecache::FontTextureCache;
 use genvec::Handle;
-use gfx::{DrawBuffer, Rect, RectFill, Size, Vec2};
-use input::{Event, KeyboardEventKind, Scancode, SerialType};
+use gfx::{DrawBuffer, Rect, RectFill, Rgba8, Size, TextureFormat, TextureTransform, Vec2};
+use history::History;
+use input::{
+    CursorShape, Event, KeyboardEventKind, PointerButton, PointerEventKind, Scancode, SerialType,
+};
+use loupe::Loupe;
+use picker::Picker;
 use renderer::Renderer;
+use stitch::StitchScale;
 use welcome::{Welcome, WelcomeUpdateData};
 
 struct Libs {
@@ -51,14 +78,48 @@ struct Libs {
 struct Globals {
     compositor: Option<*mut wayland::wl_compositor>,
     data_device_manager: Option<*mut wayland::wl_data_device_manager>,
-    outputs: Vec<*mut wayland::wl_output>,
+    primary_selection_device_manager: Option<*mut wayland::zwp_primary_selection_device_manager_v1>,
+    ext_data_control_manager: Option<*mut wayland::ext_data_control_manager_v1>,
+    wlr_data_control_manager: Option<*mut wayland::zwlr_data_control_manager_v1>,
+    // NOTE: outputs are boxed because their addresses are given to wayland listeners.
+    #[allow(clippy::vec_box)]
+    outputs: Vec<Box<wayland_output::Output>>,
     seat: Option<*mut wayland::wl_seat>,
+    seat_name: Option<u32>,
     shm: Option<*mut wayland::wl_shm>,
     fractional_scale_manager: Option<*mut wayland::wp_fractional_scale_manager_v1>,
     viewporter: Option<*mut wayland::wp_viewporter>,
     layer_shell: Option<*mut wayland::zwlr_layer_shell_v1>,
     screencopy_manager: Option<*mut wayland::zwlr_screencopy_manager_v1>,
     linux_dmabuf: Option<*mut wayland::zwp_linux_dmabuf_v1>,
+    xdg_output_manager: Option<*mut wayland::zxdg_output_manager_v1>,
+}
+
+impl Globals {
+    fn get_output(
+        &self,
+        wl_output: NonNull<wayland::wl_output>,
+    ) -> Option<&wayland_output::Output> {
+        self.outputs
+            .iter()
+            .find(|output| output.wl_output == wl_output)
+            .map(|output| output.as_ref())
+    }
+
+    fn get_output_by_name(&self, name: &str) -> anyhow::Result<&wayland_output::Output> {
+        self.outputs
+            .iter()
+            .find(|output| output.name.as_deref() == Some(name))
+            .map(|output| output.as_ref())
+            .with_context(|| {
+                let names: Vec<&str> = self
+                    .outputs
+                    .iter()
+                    .filter_map(|output| output.name.as_deref())
+                    .collect();
+                format!("output {name} not found, available: {}", names.join(", "))
+            })
+    }
 }
 
 struct Connection {
@@ -67,98 +128,202 @@ struct Connection {
 }
 
 struct Screen {
+    /// registry name of the output's global.
+    global_name: u32,
     output: NonNull<wayland::wl_output>,
 
+    // NOTE: each output is captured twice so that cursor visibility can be toggled.
     screencopy: Option<Box<wayland_screencopy::Screencopy>>,
+    screencopy_with_cursor: Option<Box<wayland_screencopy::Screencopy>>,
     overlay: Option<Box<wayland_overlay::Overlay>>,
+    /// pixels of the capture without the cursor, read lazily for the color picker.
+    pixels: Option<(Vec<u8>, Size)>,
 
     welcome: Welcome,
-    crop: Crop,
-}
-
-struct ScreenDrawOpts {
-    draw_crop_decorations: bool,
-    swap_buffers: bool,
 }
 
-impl Default for ScreenDrawOpts {
-    fn default() -> Self {
-        Self {
-            draw_crop_decorations: true,
-            swap_buffers: true,
+impl Screen {
+    fn screencopy(&self, with_cursor: bool) -> &wayland_screencopy::Screencopy {
+        if with_cursor {
+            self.screencopy_with_cursor.as_ref().unwrap()
+        } else {
+            self.screencopy.as_ref().unwrap()
         }
     }
 }
 
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum ExportTarget {
+    Clipboard,
+    File,
+    Stdout,
+}
+
 struct App {
+    config: Config,
     input: Box<wayland_input::Input>,
     clipboard: Box<wayland_clipboard::Clipboard>,
     draw_buffer: DrawBuffer,
     renderer: Renderer,
     screens: Vec<Screen>,
+    /// registry names of outputs whose capture failed after startup or whose overlay was closed.
+    /// such outputs are skipped until their global is removed.
+    failed_globals: Vec<u32>,
+    // NOTE: crop is shared by all screens, it is in global compositor coordinates.
+    crop: Crop,
+    picker: Picker,
+    annotate: Annotate,
+    history: History,
+    loupe: Loupe,
+    /// in global compositor coordinates, follows the pointer-focused screen.
+    pointer_position: Option<Vec2>,
     conn: Rc<Connection>,
 
     font_provider: FontProvider,
     font_texture_cache: FontTextureCache,
+    tinted_font_texture_cache: FontTextureCache,
     font_handle: Handle<Font>,
 
+    show_cursor: bool,
     quit_requested: bool,
     copy_requested: bool,
+    /// the clipboard needs to be served after exiting the overlay.
+    color_copied: bool,
+    /// in select mode; otherwise exiting means the selection was cancelled.
+    selection_printed: bool,
 }
 
 impl App {
-    fn init_all_screens(&mut self) -> anyhow::Result<()> {
-        assert!(self.screens.is_empty());
-        self.screens.reserve_exact(self.conn.globals.outputs.len());
+    /// adds screens for outputs that don't have one yet. returns number of added screens.
+    fn init_all_screens(&mut self) -> usize {
+        let prev_len = self.screens.len();
         for output in self.conn.globals.outputs.iter() {
+            // NOTE: output is not usable until its initial events are received.
+            if !output.done
+                || self.failed_globals.contains(&output.global_name)
+                || self
+                    .screens
+                    .iter()
+                    .any(|screen| screen.global_name == output.global_name)
+            {
+                continue;
+            }
+            log::info!(
+                "adding screen for output {}",
+                output.name.as_deref().unwrap_or("?")
+            );
             self.screens.push(Screen {
-                output: NonNull::new(*output).context("whoopsie, output is null")?,
+                global_name: output.global_name,
+                output: output.wl_output,
 
                 screencopy: None,
+                screencopy_with_cursor: None,
                 overlay: None,
+                pixels: None,
 
                 welcome: Welcome::default(),
-                crop: Crop::default(),
             });
         }
+        self.screens.len() - prev_len
+    }
+
+    /// removes screens of outputs that are gone or whose overlays were closed.
+    fn remove_gone_screens(&mut self) {
+        let outputs = &self.conn.globals.outputs;
+        let output_exists =
+            |global_name: u32| outputs.iter().any(|output| output.global_name == global_name);
+        self.failed_globals
+            .retain(|&global_name| output_exists(global_name));
+        self.screens.retain(|screen| {
+            let output_exists = output_exists(screen.global_name);
+            let overlay_closed = screen
+                .overlay
+                .as_ref()
+                .is_some_and(|overlay| overlay.closed);
+            if !output_exists || overlay_closed {
+                log::info!("removing screen of global #{}", screen.global_name);
+            }
+            if output_exists && overlay_closed {
+                // NOTE: the compositor may close the overlay again right away; the output is not
+                // captured and overlaid again while it stays around.
+                self.failed_globals.push(screen.global_name);
+            }
+            output_exists && !overlay_closed
+        });
+    }
+
+    /// handles outputs that were plugged in or out after startup.
+    fn sync_screens(&mut self) -> anyhow::Result<()> {
+        self.remove_gone_screens();
+        if self.init_all_screens() > 0 {
+            self.capture_all_screens(false)?;
+            self.overlay_all_screens()?;
+        }
         Ok(())
     }
 
-    fn capture_all_screens(&mut self) -> anyhow::Result<()> {
-        for screen in self.screens.iter_mut() {
+    /// stops using keyboard and pointer once their seat is gone.
+    fn sync_input(&mut self) {
+        if self.conn.globals.seat.is_none() {
+            self.input.release_devices();
+        }
+    }
+
+    /// captures screens that were not captured yet. failures are fatal at startup; afterwards
+    /// captures fail when outputs are unplugged mid-capture, screens of such outputs are removed.
+    fn capture_all_screens(&mut self, startup: bool) -> anyhow::Result<()> {
+        for screen in self
+            .screens
+            .iter_mut()
+            .filter(|screen| screen.screencopy.is_none())
+        {
             let screencopy = screen.screencopy.get_or_insert_with(|| {
-                wayland_screencopy::Screencopy::new_boxed(&self.conn, screen.output)
+                wayland_screencopy::Screencopy::new_boxed(&self.conn, screen.output, false)
             });
             unsafe { screencopy.capture()? };
-        }
 
-        loop {
-            let mut pending: usize = 0;
-            for (idx, screen) in self.screens.iter().enumerate() {
-                use wayland_screencopy::ScreencopyState::*;
-                match screen.screencopy.as_ref().unwrap().state {
-                    Pending => pending += 1,
-                    Ready => {}
-                    Failed => return Err(anyhow!("failed to capture screen #{idx}")),
-                }
-            }
-            if pending == 0 {
-                break;
-            }
+            let screencopy = screen.screencopy_with_cursor.get_or_insert_with(|| {
+                wayland_screencopy::Screencopy::new_boxed(&self.conn, screen.output, true)
+            });
+            unsafe { screencopy.capture()? };
+        }
 
-            unsafe {
-                (self.conn.libs.wayland.wl_display_dispatch)(
-                    self.conn.libs.wayland_display.as_ptr(),
-                )
-            };
+        let result = wait_for_screencopies(
+            &self.conn,
+            self.screens
+                .iter()
+                .flat_map(|screen| [screen.screencopy(false), screen.screencopy(true)]),
+        );
+        if startup || result.is_ok() {
+            return result;
         }
 
+        self.screens.retain(|screen| {
+            let failed = [screen.screencopy(false), screen.screencopy(true)]
+                .iter()
+                .any(|screencopy| {
+                    matches!(screencopy.state, wayland_screencopy::ScreencopyState::Failed)
+                });
+            if failed {
+                log::warn!(
+                    "removing screen of global #{}, capture failed",
+                    screen.global_name
+                );
+                // NOTE: the output is not captured again while it stays around.
+                self.failed_globals.push(screen.global_name);
+            }
+            !failed
+        });
         Ok(())
     }
 
+    /// creates overlays for screens that don't have one yet.
     fn overlay_all_screens(&mut self) -> anyhow::Result<()> {
-        for screen in self.screens.iter_mut() {
-            assert!(screen.overlay.is_none());
+        for screen in self
+            .screens
+            .iter_mut()
+            .filter(|screen| screen.overlay.is_none())
+        {
             screen.overlay = Some(wayland_overlay::Overlay::new_boxed(
                 &self.conn,
                 screen.output,
@@ -166,6 +331,9 @@ impl App {
         }
 
         loop {
+            // NOTE: outputs may be unplugged before their overlays are configured, overlays of
+            // such outputs are closed and never configured.
+            self.remove_gone_screens();
             let mut pending: usize = 0;
             for screen in self.screens.iter() {
                 pending += !screen.overlay.as_ref().unwrap().acked_first_configure as usize;
@@ -181,80 +349,160 @@ impl App {
             };
         }
 
-        let screen_draw_opts = ScreenDrawOpts::default();
         for i in 0..self.screens.len() {
-            self.draw_screen_at_index(i, &screen_draw_opts)?;
+            self.draw_screen_at_index(i)?;
             // TODO: request frame
         }
 
         Ok(())
     }
 
+    /// tears down everything but the wayland connection and the clipboard that holds the copied
+    /// image, e.g. before serving the clipboard from a detached process.
+    fn into_clipboard(self) -> (Rc<Connection>, Box<wayland_clipboard::Clipboard>) {
+        let App {
+            conn, clipboard, ..
+        } = self;
+        (conn, clipboard)
+    }
+
     fn update(&mut self) -> anyhow::Result<()> {
         while let Some(event) = self.input.events.pop_front() {
             match event {
                 Event::Keyboard(ref keyboard_event) => match keyboard_event.kind {
                     KeyboardEventKind::Press {
                         scancode: Scancode::Esc,
-                    } => self.quit_requested = true,
+                    } if self.annotate.is_editing_text() => {
+                        self.annotate.commit_text();
+                        self.history.record(&self.crop, &self.annotate);
+                        continue;
+                    }
+                    KeyboardEventKind::Press {
+                        scancode: Scancode::Esc,
+                    } => {
+                        if self.color_copied {
+                            // destroy all overlays
+                            self.screens.clear();
+                            self.copy_requested = true;
+                        } else {
+                            self.quit_requested = true;
+                        }
+                        return Ok(());
+                    }
+                    KeyboardEventKind::Press {
+                        scancode: Scancode::Enter,
+                    } if self.config.select => {
+                        self.handle_select_request();
+                        return Ok(());
+                    }
                     KeyboardEventKind::Press {
                         scancode: Scancode::C,
-                    } if keyboard_event.mods.ctrl => {
-                        self.handle_copy_request()?;
+                    } if keyboard_event.mods.ctrl && !self.config.select => {
+                        self.handle_export_request(if self.config.stdout {
+                            ExportTarget::Stdout
+                        } else {
+                            ExportTarget::Clipboard
+                        })?;
+                        return Ok(());
+                    }
+                    KeyboardEventKind::Press {
+                        scancode: Scancode::S,
+                    } if keyboard_event.mods.ctrl && !self.config.select => {
+                        self.handle_export_request(ExportTarget::File)?;
                         return Ok(());
                     }
+                    KeyboardEventKind::Press {
+                        scancode: Scancode::P,
+                    } if keyboard_event.mods.ctrl => self.show_cursor = !self.show_cursor,
+                    KeyboardEventKind::Press {
+                        scancode: Scancode::Z,
+                    } if keyboard_event.mods.ctrl && !self.crop.is_dragging() => {
+                        // NOTE: text that is being edited is undone as a whole.
+                        self.annotate.commit_text();
+                        self.history.record(&self.crop, &self.annotate);
+                        if keyboard_event.mods.shift {
+                            self.history.redo(&mut self.crop, &mut self.annotate);
+                        } else {
+                            self.history.undo(&mut self.crop, &mut self.annotate);
+                        }
+                    }
+                    KeyboardEventKind::Press {
+                        scancode: Scancode::I,
+                    } if keyboard_event.mods.ctrl => {
+                        self.picker.toggle();
+                        if self.annotate.active {
+                            self.annotate.toggle();
+                        }
+                    }
+                    KeyboardEventKind::Press {
+                        scancode: Scancode::D,
+                    } if keyboard_event.mods.ctrl && !self.config.select => {
+                        self.annotate.toggle();
+                        self.picker.active = false;
+                    }
                     _ => {}
                 },
                 _ => {}
             }
 
-            for i in 0..self.screens.len() {
-                // NOTE: this is ugly, but i don't really care.
-                //
-                // i want to be able to iterate all the screens and remove crops from other screens
-                // that weren't updated.
-                // to ensure that this one will not be updated i check i == j.
-                let screen = unsafe { &mut *(&mut self.screens[i] as *mut _) as &mut Screen };
-
-                let overlay = screen.overlay.as_ref().unwrap();
-
-                // NOTE: keyboard surface id may not match with pointer surface id; i want to
-                // operate on pointer-focused surface.
-                let screen_surface_id = wayland_input::get_surface_id(overlay.surface);
-                let Some(pointer_surface_id) = self.input.pointer_focused_surface_id else {
-                    continue;
-                };
-                let this_screen_focused = screen_surface_id == pointer_surface_id;
-
-                let logical_size = overlay.logical_size.unwrap();
-                let view_rect = Rect::new(Vec2::ZERO, logical_size.as_vec2());
+            // NOTE: text is typed wherever the pointer is, even outside of overlays.
+            if let Event::Keyboard(ref keyboard_event) = event {
+                if self.annotate.active {
+                    self.annotate.update_keyboard(keyboard_event);
+                    self.history.record(&self.crop, &self.annotate);
+                }
+            }
 
-                if this_screen_focused {
-                    let crop_updated = screen.crop.update(&event, CropUpdateData { view_rect });
+            // NOTE: keyboard surface id may not match with pointer surface id; i want to
+            // operate on pointer-focused surface.
+            let Some(focused_screen_idx) =
+                self.input
+                    .pointer_focused_surface_id
+                    .and_then(|pointer_surface_id| {
+                        self.screens.iter().position(|screen| {
+                            let overlay = screen.overlay.as_ref().unwrap();
+                            wayland_input::get_surface_id(overlay.surface) == pointer_surface_id
+                        })
+                    })
+            else {
+                continue;
+            };
 
-                    if let Some(cursor_shape) = screen.crop.cursor {
-                        self.input.set_cursor_shape(cursor_shape)?;
-                    }
+            if let Event::Pointer(pointer_event) = &event {
+                self.pointer_position = match pointer_event.kind {
+                    PointerEventKind::Leave => None,
+                    _ => Some(self.screen_rect(focused_screen_idx).min + pointer_event.position),
+                };
+            }
 
-                    if crop_updated {
-                        // remove crops from other screens
-                        for j in 0..self.screens.len() {
-                            if i == j {
-                                continue;
-                            }
-                            let other_screen = &mut self.screens[j];
-                            other_screen.crop.crop_rect = None;
-                        }
-                    }
+            if self.picker.active {
+                self.update_picker(&event, focused_screen_idx)?;
+                if self.quit_requested {
+                    return Ok(());
                 }
+            } else if self.annotate.active {
+                self.annotate
+                    .update(&event, self.screen_rect(focused_screen_idx));
+                self.input.set_cursor_shape(CursorShape::Crosshair)?;
+            } else {
+                self.crop.update(&event, CropUpdateData {
+                    view_rect: self.screen_rect(focused_screen_idx),
+                    bounds: self.desktop_rect(),
+                });
+                if let Some(cursor_shape) = self.crop.cursor {
+                    self.input.set_cursor_shape(cursor_shape)?;
+                }
+            }
+            self.history.record(&self.crop, &self.annotate);
 
+            for (i, screen) in self.screens.iter_mut().enumerate() {
+                let logical_size = screen.overlay.as_ref().unwrap().logical_size.unwrap();
                 screen.welcome.update(&event, WelcomeUpdateData {
-                    view_rect,
-                    any_crop_has_selection: self
-                        .screens
-                        .iter()
-                        .any(|screen| screen.crop.crop_rect.is_some()),
-                    this_screen_focused,
+                    view_rect: Rect::new(Vec2::ZERO, logical_size.as_vec2()),
+                    any_crop_has_selection: self.crop.crop_rect.is_some(),
+                    this_screen_focused: i == focused_screen_idx,
+                    select: self.config.select,
+                    other_mode_active: self.picker.active || self.annotate.active,
                     font_provider: &self.font_provider,
                     font_handle: self.font_handle,
                 });
@@ -264,22 +512,136 @@ impl App {
         Ok(())
     }
 
-    fn draw_screen_at_index(
-        &mut self,
-        index: usize,
-        draw_opts: &ScreenDrawOpts,
-    ) -> anyhow::Result<()> {
+    fn update_picker(&mut self, event: &Event, screen_idx: usize) -> anyhow::Result<()> {
+        let Event::Pointer(pointer_event) = event else {
+            return Ok(());
+        };
+        let position = self.screen_rect(screen_idx).min + pointer_event.position;
+
+        match pointer_event.kind {
+            PointerEventKind::Enter | PointerEventKind::Motion { .. } => {
+                self.picker.hovered = self
+                    .color_at(screen_idx, position)?
+                    .map(|color| (position, color));
+            }
+            PointerEventKind::Leave => self.picker.hovered = None,
+            PointerEventKind::Press {
+                button: PointerButton::Left,
+            } => {
+                if let Some(color) = self.picker.pick() {
+                    self.handle_color_pick(color)?;
+                }
+            }
+            _ => {}
+        }
+        self.input.set_cursor_shape(CursorShape::Crosshair)
+    }
+
+    /// color of the pixel at the position (in global compositor coordinates) on the screen.
+    fn color_at(&mut self, index: usize, position: Vec2) -> anyhow::Result<Option<Rgba8>> {
+        let screen_rect = self.screen_rect(index);
+
+        if self.screens[index].pixels.is_none() {
+            let screen = &self.screens[index];
+            let output = self
+                .conn
+                .globals
+                .get_output(screen.output)
+                .context("output is gone")?;
+            let sources = [stitch_source(output, screen.screencopy(false), screen_rect)?];
+            // NOTE: native layout of a single source is its own pixels.
+            let layout = stitch::layout(screen_rect, &sources, StitchScale::Native);
+            let size = layout.size;
+
+            self.draw_buffer.clear();
+            stitch::draw(&mut self.draw_buffer, &sources, &layout);
+            let pixels = unsafe {
+                self.conn.libs.egl_context.make_current_surfaceless()?;
+                self.renderer.draw_offscreen(size, &self.draw_buffer)?
+            };
+            self.screens[index].pixels = Some((pixels, size));
+        }
+        let (pixels, size) = self.screens[index].pixels.as_ref().unwrap();
+
+        let pixel = (position - screen_rect.min) * (size.as_vec2() / screen_rect.size());
+        if pixel.x < 0.0 || pixel.y < 0.0 {
+            return Ok(None);
+        }
+        let (x, y) = (pixel.x as usize, pixel.y as usize);
+        if x >= size.width as usize || y >= size.height as usize {
+            return Ok(None);
+        }
+        let offset = (y * size.width as usize + x) * 4;
+        Ok(Some(Rgba8::new(
+            pixels[offset],
+            pixels[offset + 1],
+            pixels[offset + 2],
+            pixels[offset + 3],
+        )))
+    }
+
+    fn handle_color_pick(&mut self, color: Rgba8) -> anyhow::Result<()> {
+        let text = self.config.color_format.format(color);
+        if self.config.stdout {
+            println!("{text}");
+            self.quit_requested = true;
+            return Ok(());
+        }
+
+        let serial = self
+            .input
+            .serial_tracker
+            .get_serial(SerialType::KeyboardEnter);
+        let selections = wayland_clipboard::Selections {
+            clipboard: self.config.clipboard,
+            primary: self.config.primary_selection,
+        };
+        self.clipboard.offer_text(serial, text, selections)?;
+        self.color_copied = true;
+
+        Ok(())
+    }
+
+    /// rect of the screen in global compositor coordinates.
+    fn screen_rect(&self, index: usize) -> Rect {
+        let screen = &self.screens[index];
+        let logical_size = screen.overlay.as_ref().unwrap().logical_size.unwrap();
+        let position = self
+            .conn
+            .globals
+            .get_output(screen.output)
+            .and_then(|output| output.logical_rect())
+            .map_or(Vec2::ZERO, |rect| rect.min);
+        Rect::new(position, position + logical_size.as_vec2())
+    }
+
+    /// union of all screen rects.
+    fn desktop_rect(&self) -> Rect {
+        (0..self.screens.len())
+            .map(|i| self.screen_rect(i))
+            .reduce(|a, b| a.union(&b))
+            .unwrap_or_default()
+    }
+
+    fn draw_screen_at_index(&mut self, index: usize) -> anyhow::Result<()> {
+        let screen_rect = self.screen_rect(index);
         let screen = &mut self.screens[index];
 
-        let screencopy = screen.screencopy.as_ref().unwrap().as_ref();
+        let screencopy = screen.screencopy(self.show_cursor);
         let overlay = screen.overlay.as_ref().unwrap();
 
         let fractional_scale = overlay.fractional_scale.unwrap_or(1.0);
         let logical_size = overlay.logical_size.unwrap();
         let view_rect = Rect::new(Vec2::ZERO, logical_size.as_vec2());
 
+        let output = self
+            .conn
+            .globals
+            .get_output(screen.output)
+            .context("output is gone")?;
+
         let window_surface = overlay.window_surface.as_ref().unwrap();
-        let dmabuf = screencopy.dmabuf.as_ref().unwrap();
+        let screencopy_texture = screencopy.buffer.as_ref().unwrap().gl_texture();
 
         unsafe {
             self.conn
@@ -293,22 +655,90 @@ impl App {
 
         self.draw_buffer.clear();
 
-        self.draw_buffer
-            .push_rect_filled(view_rect, RectFill::Texture {
-                handle: dmabuf.gl_texture.handle,
-                coords: Rect::new(Vec2::splat(0.0), Vec2::splat(1.0)),
-            });
+        self.draw_buffer.push_rect_textured(
+            view_rect,
+            screencopy_texture.handle,
+            Rect::new(Vec2::splat(0.0), Vec2::splat(1.0)),
+            output.texture_transform(screencopy.y_invert()),
+        );
+        for redaction in self.annotate.redactions() {
+            redaction.draw_preview(
+                &mut self.draw_buffer,
+                screen_rect,
+                self.config.pixelate_block_size,
+                screencopy_texture.handle,
+                output.texture_transform(screencopy.y_invert()),
+            );
+        }
 
-        if draw_opts.draw_crop_decorations {
-            if screen.crop.crop_rect.is_some() {
-                screen.crop.draw(&mut self.draw_buffer);
-            } else {
-                // TODO: should this be state of the crop?
-                self.draw_buffer
-                    .push_rect_filled(view_rect, RectFill::Color(crop::theme::OUTSIDE_BG));
-            }
+        let mut annotate_draw_data = annotate::AnnotateDrawData {
+            font_provider: &mut self.font_provider,
+            font_handle: self.font_handle,
+            font_texture_cache: &mut self.font_texture_cache,
+            tinted_font_texture_cache: &mut self.tinted_font_texture_cache,
+            gl_lib: self.conn.libs.gl,
+        };
+        self.annotate.draw(
+            &mut self.draw_buffer,
+            screen_rect.min,
+            1.0,
+            &mut annotate_draw_data,
+        );
+
+        if self.picker.active {
+            // NOTE: colors must not be dimmed while picking.
+            let mut label_draw_data = label::LabelDrawData {
+                font_provider: &self.font_provider,
+                font_texture_cache: &mut self.font_texture_cache,
+                font_handle: self.font_handle,
+                gl_lib: self.conn.libs.gl,
+            };
+            self.picker
+                .draw(&mut self.draw_buffer, screen_rect, &mut label_draw_data);
+        } else if self.crop.crop_rect.is_some() {
+            self.crop.draw(&mut self.draw_buffer, screen_rect);
+        } else {
+            // TODO: should this be state of the crop?
+            self.draw_buffer
+                .push_rect_filled(view_rect, RectFill::Color(crop::theme::OUTSIDE_BG));
         }
 
+        // NOTE: the loupe helps to hit exact pixels while dragging handles or picking colors.
+        let show_loupe = self.picker.active || self.crop.is_dragging();
+        if let Some(position) = self.pointer_position.filter(|_| show_loupe) {
+            let buffer_size = screencopy
+                .buffer_size()
+                .context("screencopy has no buffer")?;
+            let texture_transform = output.texture_transform(screencopy.y_invert());
+            let mut loupe_draw_data = loupe::LoupeDrawData {
+                texture_handle: screencopy_texture.handle,
+                texture_transform,
+                texture_size: texture_transform.apply_size(buffer_size),
+                font_provider: &self.font_provider,
+                font_handle: self.font_handle,
+                font_texture_cache: &mut self.font_texture_cache,
+                gl_lib: self.conn.libs.gl,
+            };
+            let color = self.picker.hovered.map(|(_, color)| color);
+            self.loupe.draw(
+                &mut self.draw_buffer,
+                screen_rect,
+                position,
+                color,
+                &mut loupe_draw_data,
+            );
+        }
+
+        let mut annotate_draw_data = annotate::AnnotateDrawData {
+            font_provider: &mut self.font_provider,
+            font_handle: self.font_handle,
+            font_texture_cache: &mut self.font_texture_cache,
+            tinted_font_texture_cache: &mut self.tinted_font_texture_cache,
+            gl_lib: self.conn.libs.gl,
+        };
+        self.annotate
+            .draw_ui(&mut self.draw_buffer, screen_rect, &mut annotate_draw_data);
+
         screen
             .welcome
             .draw(&mut self.draw_buffer, welcome::WelcomeDrawData {
@@ -323,33 +753,154 @@ impl App {
                 .draw(logical_size, fractional_scale, &self.draw_buffer);
         }
 
-        if draw_opts.swap_buffers {
-            unsafe {
-                self.conn
-                    .libs
-                    .egl_context
-                    .swap_buffers(window_surface.handle)?;
-            }
+        unsafe {
+            self.conn
+                .libs
+                .egl_context
+                .swap_buffers(window_surface.handle)?;
         }
 
         Ok(())
     }
 
-    fn draw(&mut self, screen_draw_opts: ScreenDrawOpts) -> anyhow::Result<()> {
+    fn draw(&mut self) -> anyhow::Result<()> {
         for i in 0..self.screens.len() {
-            self.draw_screen_at_index(i, &screen_draw_opts)?;
+            self.draw_screen_at_index(i)?;
         }
         Ok(())
     }
 
-    fn handle_copy_request(&mut self) -> anyhow::Result<()> {
-        let Some(screen_idx) = self
-            .screens
-            .iter()
-            .enumerate()
-            .find(|(_, screen)| screen.crop.crop_rect.is_some())
-            .map(|(idx, _)| idx)
-        else {
+    /// stitches pixels of all screens that intersect the crop rect.
+    fn read_crop_image(&mut self, crop_rect: Rect) -> anyhow::Result<encoder::Image> {
+        let mut sources = Vec::new();
+        let mut presented_at = None;
+        for (i, screen) in self.screens.iter().enumerate() {
+            let screen_rect = self.screen_rect(i);
+            if !screen_rect.intersects(&crop_rect) {
+                continue;
+            }
+            let output = self
+                .conn
+                .globals
+                .get_output(screen.output)
+                .context("output is gone")?;
+            let screencopy = screen.screencopy(self.show_cursor);
+            sources.push(stitch_source(output, screencopy, screen_rect)?);
+            presented_at = earliest(presented_at, screencopy.presented_at);
+        }
+
+        // NOTE: the context must be current before drawing, text annotations may upload glyphs.
+        unsafe { self.conn.libs.egl_context.make_current_surfaceless()? };
+
+        // NOTE: redactions and annotations need a uniform scale, thus outputs get their native
+        // density after them.
+        let layout = stitch::layout(crop_rect, &sources, StitchScale::Max);
+        let mut size = layout.size;
+        self.draw_buffer.clear();
+        stitch::draw(&mut self.draw_buffer, &sources, &layout);
+        let mut pixels = unsafe { self.renderer.draw_offscreen(size, &self.draw_buffer)? };
+
+        // NOTE: annotations are baked into the image, crop decorations are not. redactions are
+        // applied to pixels so that nothing of the original remains in the encoded image; other
+        // annotations are drawn on top of them.
+        let scale = size.width as f32 / crop_rect.width();
+        for redaction in self.annotate.redactions() {
+            redaction.apply(
+                &mut pixels,
+                size,
+                crop_rect,
+                scale,
+                self.config.pixelate_block_size,
+            );
+        }
+        if self.annotate.has_drawings() {
+            let texture = unsafe {
+                gl::Texture2D::new(
+                    self.conn.libs.gl,
+                    size.width,
+                    size.height,
+                    TextureFormat::Rgba8Unorm,
+                    Some(&pixels),
+                )
+            };
+            self.draw_buffer.clear();
+            self.draw_buffer.push_rect_textured(
+                Rect::new(Vec2::ZERO, size.as_vec2()),
+                texture.handle,
+                Rect::new(Vec2::ZERO, Vec2::splat(1.0)),
+                TextureTransform::default(),
+            );
+            let mut annotate_draw_data = annotate::AnnotateDrawData {
+                font_provider: &mut self.font_provider,
+                font_handle: self.font_handle,
+                font_texture_cache: &mut self.font_texture_cache,
+                tinted_font_texture_cache: &mut self.tinted_font_texture_cache,
+                gl_lib: self.conn.libs.gl,
+            };
+            self.annotate.draw(
+                &mut self.draw_buffer,
+                crop_rect.min,
+                scale,
+                &mut annotate_draw_data,
+            );
+            pixels = unsafe { self.renderer.draw_offscreen(size, &self.draw_buffer)? };
+        }
+        if self.config.stitch_scale == StitchScale::Native {
+            let native_layout = stitch::layout(crop_rect, &sources, StitchScale::Native);
+            if native_layout.dst_rects != layout.dst_rects {
+                let texture = unsafe {
+                    gl::Texture2D::new(
+                        self.conn.libs.gl,
+                        size.width,
+                        size.height,
+                        TextureFormat::Rgba8Unorm,
+                        Some(&pixels),
+                    )
+                };
+                self.draw_buffer.clear();
+                stitch::draw_repacked(
+                    &mut self.draw_buffer,
+                    texture.handle,
+                    &layout,
+                    &native_layout,
+                );
+                size = native_layout.size;
+                pixels = unsafe { self.renderer.draw_offscreen(size, &self.draw_buffer)? };
+            }
+        }
+        let (pixels, size) = scale_for_export(&self.config, crop_rect, pixels, size);
+
+        Ok(encoder::Image {
+            pixels,
+            size,
+            metadata: capture_metadata(&self.conn, crop_rect, size, presented_at),
+        })
+    }
+
+    fn handle_select_request(&mut self) {
+        let Some(crop_rect) = self.crop.crop_rect.map(|crop_rect| crop_rect.normalize()) else {
+            return;
+        };
+
+        let output_names = output_names_in(&self.conn, crop_rect);
+        let scale = primary_output(&self.conn, crop_rect)
+            .and_then(|output| output.pixel_scale())
+            .unwrap_or(1.0);
+        let template_data = template::TemplateData {
+            outputs: &output_names,
+            geometry: crop_rect,
+        };
+        println!(
+            "{}",
+            template::expand_selection(&self.config.select_format, &template_data, scale)
+        );
+
+        self.selection_printed = true;
+        self.quit_requested = true;
+    }
+
+    fn handle_export_request(&mut self, target: ExportTarget) -> anyhow::Result<()> {
+        let Some(crop_rect) = self.crop.crop_rect.map(|crop_rect| crop_rect.normalize()) else {
             return Ok(());
         };
 
@@ -365,54 +916,421 @@ impl App {
             }
         }
 
-        // read pixels
-        let (pixels, size) = {
-            self.draw_screen_at_index(screen_idx, &ScreenDrawOpts {
-                draw_crop_decorations: false,
-                swap_buffers: false,
+        let image = self.read_crop_image(crop_rect)?;
+        let output_names = output_names_in(&self.conn, crop_rect);
+
+        // destroy all overlays
+        self.screens.clear();
+
+        let mut path = None;
+        if target == ExportTarget::File || self.config.save {
+            let saved = save_to_file(&self.config, &image, &template::TemplateData {
+                outputs: &output_names,
+                geometry: crop_rect,
             })?;
+            if !self.config.stdout {
+                println!("{}", saved.display());
+            }
+            path = Some(saved);
+        }
+
+        match target {
+            ExportTarget::Clipboard => {
+                let serial = self
+                    .input
+                    .serial_tracker
+                    .get_serial(SerialType::KeyboardEnter);
+                let clipboard_image = wayland_clipboard::ClipboardImage {
+                    image,
+                    format: self.config.format.unwrap_or_default(),
+                    encode_options: self.config.encode_options,
+                    // NOTE: file uris must be absolute.
+                    path: path.map(std::path::absolute).transpose()?,
+                };
+                let selections = wayland_clipboard::Selections {
+                    clipboard: self.config.clipboard,
+                    primary: self.config.primary_selection,
+                };
+                self.clipboard
+                    .offer_image(serial, clipboard_image, selections)?;
+                self.copy_requested = true;
+            }
+            ExportTarget::Stdout => {
+                write_to_stdout(&self.config, &image)?;
+                self.quit_requested = true;
+            }
+            // NOTE: there's nothing left to serve.
+            ExportTarget::File => self.quit_requested = true,
+        }
+
+        Ok(())
+    }
+}
+
+/// forks; the parent exits right away and the child returns, detached from the terminal.
+fn detach() -> anyhow::Result<()> {
+    // NOTE: anything buffered would otherwise be written by both processes.
+    _ = std::io::stdout().flush();
+
+    match unsafe { libc::fork() } {
+        -1 => {
+            let errno = unsafe { *libc::__errno_location() };
+            Err(anyhow!("could not fork, errno {}", errno))
+        }
+        0 => {
+            unsafe {
+                libc::setsid();
+                let dev_null = libc::open(c"/dev/null".as_ptr(), libc::O_RDWR);
+                if dev_null >= 0 {
+                    libc::dup2(dev_null, libc::STDIN_FILENO);
+                    libc::dup2(dev_null, libc::STDOUT_FILENO);
+                    libc::dup2(dev_null, libc::STDERR_FILENO);
+                    libc::close(dev_null);
+                }
+            }
+            Ok(())
+        }
+        // NOTE: the wayland connection is shared with the child, destructors (and atexit
+        // handlers, e.g. of the gl driver) must not run here.
+        _ => unsafe { libc::_exit(0) },
+    }
+}
 
-            let screen = &self.screens[screen_idx];
-            let overlay = screen.overlay.as_ref().unwrap();
+/// dispatches until the selection is replaced.
+fn serve_clipboard(conn: &Connection, clipboard: &wayland_clipboard::Clipboard) {
+    let wl_display = conn.libs.wayland_display.as_ptr();
+    // NOTE: clipboard is mutated by the data source listener while dispatching.
+    loop {
+        if clipboard.cancelled || unsafe { (conn.libs.wayland.wl_display_dispatch)(wl_display) } < 0
+        {
+            break;
+        }
+    }
+}
 
-            let fractional_scale = overlay.fractional_scale.unwrap_or(1.0) as f32;
-            let crop_rect = screen.crop.crop_rect.unwrap() * fractional_scale;
-            let view_rect = screen.crop.view_rect.unwrap() * fractional_scale;
-            assert!(view_rect.min.eq(&Vec2::ZERO));
+fn write_to_stdout(config: &Config, image: &encoder::Image) -> anyhow::Result<()> {
+    let format = config.format.unwrap_or_default();
+    let data = encoder::encode(format, image, &config.encode_options)?;
+    let mut stdout = std::io::stdout().lock();
+    stdout
+        .write_all(&data)
+        .context("could not write to stdout")?;
+    stdout.flush().context("could not flush stdout")
+}
 
-            let crop_size = Size::new(crop_rect.width() as u32, crop_rect.height() as u32);
-            let view_size = Size::new(view_rect.width() as u32, view_rect.height() as u32);
+/// true when stdout is a pipe. sockets and character devices (terminals, /dev/null) are what
+/// stdout usually is when launched from a keybinding or a service; regular files may be logs that
+/// a compositor or a service manager writes to, images must be requested explicitly there.
+fn stdout_is_redirected() -> bool {
+    let mut stat: libc::stat = unsafe { std::mem::zeroed() };
+    if unsafe { libc::fstat(libc::STDOUT_FILENO, &mut stat) } != 0 {
+        return false;
+    }
+    stat.st_mode & libc::S_IFMT == libc::S_IFIFO
+}
 
-            let pixels = unsafe { gl::read_pixels(self.conn.libs.gl, crop_rect, view_size) };
+/// like wl_display_dispatch, but gives up waiting for events once timeout passes.
+fn dispatch_timeout(conn: &Connection, timeout: Option<Duration>) -> c_int {
+    let wayland = conn.libs.wayland;
+    let display = conn.libs.wayland_display.as_ptr();
+    unsafe {
+        // NOTE: events that are already queued must be dispatched before reading more.
+        if (wayland.wl_display_prepare_read)(display) != 0 {
+            return (wayland.wl_display_dispatch_pending)(display);
+        }
+        (wayland.wl_display_flush)(display);
 
-            (pixels, crop_size)
+        let mut pollfd = libc::pollfd {
+            fd: (wayland.wl_display_get_fd)(display),
+            events: libc::POLLIN,
+            revents: 0,
         };
+        let timeout_ms = timeout.map_or(-1, |timeout| {
+            timeout.as_micros().div_ceil(1000).min(c_int::MAX as u128) as c_int
+        });
+        if libc::poll(&mut pollfd, 1, timeout_ms) > 0 {
+            if (wayland.wl_display_read_events)(display) < 0 {
+                return -1;
+            }
+        } else {
+            (wayland.wl_display_cancel_read)(display);
+        }
+        (wayland.wl_display_dispatch_pending)(display)
+    }
+}
 
-        // destroy all overlays
-        self.screens.clear();
+fn wait_for_screencopies<'a>(
+    conn: &Connection,
+    screencopies: impl Iterator<Item = &'a wayland_screencopy::Screencopy> + Clone,
+) -> anyhow::Result<()> {
+    // NOTE: all captures are waited for, even if some of them failed, so that none of them is
+    // left pending.
+    loop {
+        let mut pending: usize = 0;
+        let mut failed: Option<usize> = None;
+        for (idx, screencopy) in screencopies.clone().enumerate() {
+            use wayland_screencopy::ScreencopyState::*;
+            match screencopy.state {
+                Pending => pending += 1,
+                Ready => {}
+                Failed => failed = failed.or(Some(idx)),
+            }
+        }
+        if pending == 0 {
+            return match failed {
+                Some(idx) => Err(anyhow!("failed to capture screen #{idx}")),
+                None => Ok(()),
+            };
+        }
 
-        // TODO: encode pixels to png
-        let mut data: Vec<u8> = Vec::new();
-        let mut encoder = png::Encoder::new(&mut data, size.width, size.height);
-        encoder.set_color(png::ColorType::Rgba);
-        encoder.set_depth(png::BitDepth::Eight);
-        encoder.set_compression(png::Compression::Fast);
-        encoder
-            .write_header()
-            .context("could not write png header")?
-            .write_image_data(&pixels)
-            .context("could not write png data")?;
+        unsafe { (conn.libs.wayland.wl_display_dispatch)(conn.libs.wayland_display.as_ptr()) };
+    }
+}
 
-        let serial = self
-            .input
-            .serial_tracker
-            .get_serial(SerialType::KeyboardEnter)
-            .context("no pointer enter serial found")?;
-        self.clipboard
-            .offer_data(serial, "image/png".to_string(), data)?;
+fn stitch_source(
+    output: &wayland_output::Output,
+    screencopy: &wayland_screencopy::Screencopy,
+    logical_rect: Rect,
+) -> anyhow::Result<stitch::StitchSource> {
+    let texture_transform = output.texture_transform(screencopy.y_invert());
+    let buffer_size = screencopy
+        .buffer_size()
+        .context("screencopy has no buffer")?;
+    Ok(stitch::StitchSource {
+        logical_rect,
+        size: texture_transform.apply_size(buffer_size),
+        texture_handle: screencopy.buffer.as_ref().unwrap().gl_texture().handle,
+        texture_transform,
+    })
+}
 
-        Ok(())
+/// captures the given region (in global logical coordinates) without showing overlays.
+fn capture_geometry(
+    conn: &Rc<Connection>,
+    renderer: &Renderer,
+    config: &Config,
+    geometry: Rect,
+) -> anyhow::Result<encoder::Image> {
+    // NOTE: outputs may be removed while dispatching, thus only pointers are held.
+    let Some((wl_output, output_rect)) = conn
+        .globals
+        .outputs
+        .iter()
+        .filter_map(|output| Some((output.wl_output, output.logical_rect()?)))
+        .find(|(_, output_rect)| {
+            output_rect.contains(&geometry.min) && output_rect.contains(&geometry.max)
+        })
+    else {
+        // NOTE: geometry spans multiple outputs.
+        return capture_stitched(conn, renderer, config, geometry);
+    };
+
+    let mut screencopy = wayland_screencopy::Screencopy::new_boxed(conn, wl_output, config.cursor);
+    unsafe { screencopy.capture_region(geometry.translate(&(Vec2::ZERO - output_rect.min)))? };
+    wait_for_screencopies(conn, std::iter::once(screencopy.as_ref()))?;
+
+    let output = conn
+        .globals
+        .get_output(wl_output)
+        .context("output is gone")?;
+    let texture_transform = output.texture_transform(screencopy.y_invert());
+    let buffer_size = screencopy
+        .buffer_size()
+        .context("screencopy has no buffer")?;
+    let size = texture_transform.apply_size(buffer_size);
+
+    let mut draw_buffer = DrawBuffer::default();
+    draw_buffer.push_rect_textured(
+        Rect::new(Vec2::ZERO, size.as_vec2()),
+        screencopy.buffer.as_ref().unwrap().gl_texture().handle,
+        Rect::new(Vec2::splat(0.0), Vec2::splat(1.0)),
+        texture_transform,
+    );
+    let pixels = unsafe { renderer.draw_offscreen(size, &draw_buffer)? };
+    let (pixels, size) = scale_for_export(config, geometry, pixels, size);
+
+    Ok(encoder::Image {
+        pixels,
+        size,
+        metadata: capture_metadata(conn, geometry, size, screencopy.presented_at),
+    })
+}
+
+/// captures all outputs that intersect the region (in global logical coordinates) and stitches
+/// them into a single image.
+fn capture_stitched(
+    conn: &Rc<Connection>,
+    renderer: &Renderer,
+    config: &Config,
+    region: Rect,
+) -> anyhow::Result<encoder::Image> {
+    // NOTE: outputs may be removed while dispatching, thus only pointers are held.
+    let outputs: Vec<(NonNull<wayland::wl_output>, Rect)> = conn
+        .globals
+        .outputs
+        .iter()
+        .filter_map(|output| Some((output.wl_output, output.logical_rect()?)))
+        .filter(|(_, output_rect)| output_rect.intersects(&region))
+        .collect();
+    if outputs.is_empty() {
+        return Err(anyhow!("region does not intersect any output"));
     }
+
+    let mut screencopies = Vec::with_capacity(outputs.len());
+    for (wl_output, _) in outputs.iter() {
+        let mut screencopy =
+            wayland_screencopy::Screencopy::new_boxed(conn, *wl_output, config.cursor);
+        unsafe { screencopy.capture()? };
+        screencopies.push(screencopy);
+    }
+    wait_for_screencopies(
+        conn,
+        screencopies.iter().map(|screencopy| screencopy.as_ref()),
+    )?;
+
+    let mut sources = Vec::with_capacity(outputs.len());
+    for ((wl_output, output_rect), screencopy) in outputs.iter().zip(screencopies.iter()) {
+        let output = conn
+            .globals
+            .get_output(*wl_output)
+            .context("output is gone")?;
+        sources.push(stitch_source(output, screencopy, *output_rect)?);
+    }
+
+    let layout = stitch::layout(region, &sources, config.stitch_scale);
+    let mut draw_buffer = DrawBuffer::default();
+    stitch::draw(&mut draw_buffer, &sources, &layout);
+    let pixels = unsafe { renderer.draw_offscreen(layout.size, &draw_buffer)? };
+    let (pixels, size) = scale_for_export(config, region, pixels, layout.size);
+
+    let presented_at = screencopies.iter().fold(None, |acc, screencopy| {
+        earliest(acc, screencopy.presented_at)
+    });
+    Ok(encoder::Image {
+        pixels,
+        size,
+        metadata: capture_metadata(conn, region, size, presented_at),
+    })
+}
+
+/// resamples the capture of the region (in global logical coordinates) to the size defined by
+/// export scale.
+fn scale_for_export(config: &Config, region: Rect, pixels: Vec<u8>, size: Size) -> (Vec<u8>, Size) {
+    let new_size = config.export_scale.apply(region.size(), size);
+    if new_size == size {
+        return (pixels, size);
+    }
+    log::info!(
+        "resampling {}x{} to {}x{}",
+        size.width,
+        size.height,
+        new_size.width,
+        new_size.height
+    );
+    let pixels = resample::resample(&pixels, size, new_size, config.resample_filter);
+    (pixels, new_size)
+}
+
+fn earliest(a: Option<Duration>, b: Option<Duration>) -> Option<Duration> {
+    match (a, b) {
+        (Some(a), Some(b)) => Some(a.min(b)),
+        (a, b) => a.or(b),
+    }
+}
+
+/// the output that covers the most of the region defines pixel density.
+fn capture_metadata(
+    conn: &Connection,
+    region: Rect,
+    size: Size,
+    presented_at: Option<Duration>,
+) -> encoder::ImageMetadata {
+    let primary_output = primary_output(conn, region);
+    let output_names = output_names_in(conn, region);
+    metadata::CaptureMetadata {
+        outputs: &output_names,
+        geometry: region,
+        size,
+        time: presented_at.unwrap_or_else(|| {
+            SystemTime::now()
+                .duration_since(SystemTime::UNIX_EPOCH)
+                .unwrap_or_default()
+        }),
+        output_pixels_per_meter: primary_output.and_then(|output| output.pixels_per_meter()),
+        output_scale: primary_output
+            .and_then(|output| output.pixel_scale())
+            .unwrap_or(1.0),
+    }
+    .to_image_metadata()
+}
+
+/// the output that covers the most of the region.
+fn primary_output(conn: &Connection, region: Rect) -> Option<&wayland_output::Output> {
+    let area = |rect: &Rect| {
+        let rect = rect.constrain_to(&region);
+        rect.width() * rect.height()
+    };
+    conn.globals
+        .outputs
+        .iter()
+        .filter_map(|output| Some((output, output.logical_rect()?)))
+        .filter(|(_, output_rect)| output_rect.intersects(&region))
+        .max_by(|(_, a), (_, b)| area(a).total_cmp(&area(b)))
+        .map(|(output, _)| output.as_ref())
+}
+
+/// rect of the whole desktop, i.e. union of all outputs, or only of the named output.
+fn desktop_rect(conn: &Connection, output_name: Option<&str>) -> anyhow::Result<Rect> {
+    match output_name {
+        Some(name) => conn.globals.get_output_by_name(name)?.logical_rect(),
+        None => conn
+            .globals
+            .outputs
+            .iter()
+            .filter_map(|output| output.logical_rect())
+            .reduce(|a, b| a.union(&b)),
+    }
+    .context("logical geometry of outputs is unknown")
+}
+
+fn output_names_in(conn: &Connection, region: Rect) -> Vec<String> {
+    conn.globals
+        .outputs
+        .iter()
+        .filter(|output| {
+            output
+                .logical_rect()
+                .is_some_and(|output_rect| output_rect.intersects(&region))
+        })
+        .filter_map(|output| output.name.clone())
+        .collect()
+}
+
+/// the format is taken from config, otherwise from the extension of the expanded filename.
+fn save_to_file(
+    config: &Config,
+    image: &encoder::Image,
+    template_data: &template::TemplateData,
+) -> anyhow::Result<PathBuf> {
+    let time = unsafe { libc::time(null_mut()) };
+    let dir = config.output_dir.clone().or_else(template::pictures_dir);
+    let mut path = template::expand(&config.filename, dir.as_ref(), time, template_data)?;
+
+    let path_format = ImageFormat::from_path(&path);
+    let format = config.format.or(path_format).unwrap_or_default();
+    if path_format.is_none() {
+        path.as_mut_os_string().push(".");
+        path.as_mut_os_string().push(format.extension());
+    }
+    let data = encoder::encode(format, image, &config.encode_options)?;
+
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)
+            .with_context(|| format!("could not create {}", parent.display()))?;
+    }
+    fs::write(&path, &data).with_context(|| format!("could not write {}", path.display()))?;
+    log::info!("saved {}", path.display());
+    Ok(path)
 }
 
 unsafe extern "C" fn handle_registry_global(
@@ -448,40 +1366,120 @@ unsafe extern "C" fn handle_registry_global(
             bind_assign!(data_device_manager, wl_data_device_manager_interface)
         }
         "wl_output" => {
-            conn.globals.outputs.push(wayland::wl_registry_bind(
+            // NOTE: listener does not know about events of versions newer then the one generated
+            // bindings were generated from.
+            let wl_output = wayland::wl_registry_bind(
                 conn.libs.wayland,
                 wl_registry,
                 name,
                 &wayland::wl_output_interface,
-                version,
-            ) as _);
+                version.min(wayland::wl_output_interface.version as u32),
+            ) as *mut wayland::wl_output;
+            let wl_output = NonNull::new(wl_output).expect("could not bind output");
+            let mut output = wayland_output::Output::new_boxed(conn.libs.wayland, name, wl_output);
+            if let Some(xdg_output_manager) = conn.globals.xdg_output_manager {
+                output
+                    .bind_xdg_output(conn.libs.wayland, xdg_output_manager)
+                    .expect("could not bind xdg output");
+            }
+            conn.globals.outputs.push(output);
+            log::info!("bound {interface}");
+        }
+        "wl_seat" => {
+            // NOTE: input is set up for a single seat.
+            if conn.globals.seat.is_some() {
+                log::info!("ignoring additional seat");
+                return;
+            }
+            bind_assign!(seat, wl_seat_interface);
+            conn.globals.seat_name = Some(name);
         }
-        "wl_seat" => bind_assign!(seat, wl_seat_interface),
         "wl_shm" => bind_assign!(shm, wl_shm_interface),
+        "ext_data_control_manager_v1" => bind_assign!(
+            ext_data_control_manager,
+            ext_data_control_manager_v1_interface
+        ),
         "wp_fractional_scale_manager_v1" => bind_assign!(
             fractional_scale_manager,
             wp_fractional_scale_manager_v1_interface
         ),
         "wp_viewporter" => bind_assign!(viewporter, wp_viewporter_interface),
         "zwlr_layer_shell_v1" => bind_assign!(layer_shell, zwlr_layer_shell_v1_interface),
+        "zwlr_data_control_manager_v1" => bind_assign!(
+            wlr_data_control_manager,
+            zwlr_data_control_manager_v1_interface
+        ),
         "zwlr_screencopy_manager_v1" => {
             bind_assign!(screencopy_manager, zwlr_screencopy_manager_v1_interface)
         }
         "zwp_linux_dmabuf_v1" => bind_assign!(linux_dmabuf, zwp_linux_dmabuf_v1_interface),
+        "zwp_primary_selection_device_manager_v1" => bind_assign!(
+            primary_selection_device_manager,
+            zwp_primary_selection_device_manager_v1_interface
+        ),
+        "zxdg_output_manager_v1" => {
+            bind_assign!(xdg_output_manager, zxdg_output_manager_v1_interface);
+            // NOTE: outputs that were bound before the manager.
+            let xdg_output_manager = conn.globals.xdg_output_manager.unwrap();
+            for output in conn.globals.outputs.iter_mut() {
+                output
+                    .bind_xdg_output(conn.libs.wayland, xdg_output_manager)
+                    .expect("could not bind xdg output");
+            }
+        }
         _ => {
             log::debug!("unused interface: {interface}");
         }
     }
 }
 
+unsafe extern "C" fn handle_registry_global_remove(
+    data: *mut c_void,
+    _wl_registry: *mut wayland::wl_registry,
+    name: u32,
+) {
+    let conn = &mut *(data as *mut Connection);
+
+    if let Some(idx) = conn
+        .globals
+        .outputs
+        .iter()
+        .position(|output| output.global_name == name)
+    {
+        // NOTE: screens that belong to the output are removed by the app.
+        let output = conn.globals.outputs.remove(idx);
+        log::info!("removed output {}", output.name.as_deref().unwrap_or("?"));
+    } else if conn.globals.seat_name == Some(name) {
+        log::warn!("seat was removed");
+        // NOTE: keyboard and pointer of the seat are released by the app.
+        let seat = conn.globals.seat.take().unwrap();
+        conn.globals.seat_name = None;
+        // NOTE: release request was added in version 5, older seats can only be destroyed
+        // locally.
+        if (conn.libs.wayland.wl_proxy_get_version)(seat as *mut wayland::wl_proxy) >= 5 {
+            wayland::wl_seat_release(conn.libs.wayland, seat);
+        } else {
+            (conn.libs.wayland.wl_proxy_destroy)(seat as *mut wayland::wl_proxy);
+        }
+    } else {
+        log::debug!("removed global #{name}");
+    }
+}
+
 const WL_REGISTRY_LISTENER: wayland::wl_registry_listener = wayland::wl_registry_listener {
     global: handle_registry_global,
-    global_remove: wayland::noop_listener!(),
+    global_remove: handle_registry_global_remove,
 };
 
 fn main() -> anyhow::Result<()> {
     env_logger::init();
 
+    let mut config = Config::load()?;
+    if config.help {
+        print!("{}", config::USAGE);
+        return Ok(());
+    }
+
     let wayland_lib = wayland::Lib::load()?.leak();
     let wayland_egl_lib = wayland_egl::Lib::load()?.leak();
     let wayland_cursor_lib = wayland_cursor::Lib::load()?.leak();
@@ -531,43 +1529,157 @@ fn main() -> anyhow::Result<()> {
             Rc::get_mut(&mut conn).unwrap() as *mut Connection as *mut c_void,
         );
         (wayland_lib.wl_display_roundtrip)(wl_display);
+        // NOTE: second roundtrip is needed to receive initial events of globals that were bound
+        // during the first one (e.g. output geometry).
+        (wayland_lib.wl_display_roundtrip)(wl_display);
+    }
+
+    for output in conn.globals.outputs.iter() {
+        log::info!(
+            "output {} ({}): logical rect {:?}, mode {:?}, scale {}, transform {}",
+            output.name.as_deref().unwrap_or("?"),
+            output.description.as_deref().unwrap_or("?"),
+            output.logical_rect(),
+            output.current_mode(),
+            output.scale,
+            output.transform,
+        );
+    }
+
+    if config.geometry.is_some() || config.desktop || config.output.is_some() {
+        if config.select {
+            return Err(anyhow!("--select can not be combined with capture modes"));
+        }
+        let renderer = unsafe { Renderer::new(gl_lib)? };
+        let (region, image) = match config.geometry {
+            Some(geometry) => (
+                geometry,
+                capture_geometry(&conn, &renderer, &config, geometry)?,
+            ),
+            None => {
+                let region = desktop_rect(&conn, config.output.as_deref())?;
+                (region, capture_stitched(&conn, &renderer, &config, region)?)
+            }
+        };
+        let mut path = None;
+        if config.save {
+            let output_names = output_names_in(&conn, region);
+            let saved = save_to_file(&config, &image, &template::TemplateData {
+                outputs: &output_names,
+                geometry: region,
+            })?;
+            if !config.stdout {
+                println!("{}", saved.display());
+            }
+            path = Some(saved);
+        }
+        if config.stdout || !(config.save || config.copy) {
+            write_to_stdout(&config, &image)?;
+        }
+        if config.copy {
+            // NOTE: without the overlay there's no keyboard focus, thus no serial; this only
+            // works with data control.
+            let clipboard_image = wayland_clipboard::ClipboardImage {
+                image,
+                format: config.format.unwrap_or_default(),
+                encode_options: config.encode_options,
+                path: path.map(std::path::absolute).transpose()?,
+            };
+            let selections = wayland_clipboard::Selections {
+                clipboard: config.clipboard,
+                primary: config.primary_selection,
+            };
+            let mut clipboard = wayland_clipboard::Clipboard::new_boxed(&conn);
+            clipboard.offer_image(None, clipboard_image, selections)?;
+
+            if config.detach {
+                // NOTE: see the interactive counterpart.
+                clipboard.encode_all()?;
+                drop(renderer);
+                unsafe { conn.libs.egl_context.terminate() };
+                detach()?;
+            }
+            serve_clipboard(&conn, &clipboard);
+            if config.detach {
+                unsafe { libc::_exit(0) }
+            }
+        }
+        return Ok(());
     }
 
+    // NOTE: in non-interactive modes a redirected stdout is expected to receive the path of the
+    // saved file, so detection only applies here.
+    if !config.select && !config.stdout && stdout_is_redirected() {
+        log::info!("stdout is redirected, the image will be written to it");
+        config.stdout = true;
+    }
+
+    let picker = Picker::new(config.pick, config.color_format);
     let mut app = App {
+        show_cursor: config.cursor,
+        config,
         input: wayland_input::Input::new_boxed(&conn)?,
         clipboard: wayland_clipboard::Clipboard::new_boxed(&conn),
         draw_buffer: DrawBuffer::default(),
         renderer: unsafe { Renderer::new(gl_lib)? },
         screens: Vec::new(),
+        failed_globals: Vec::new(),
+        crop: Crop::default(),
+        picker,
+        annotate: Annotate::default(),
+        history: History::default(),
+        loupe: Loupe::default(),
+        pointer_position: None,
         conn,
 
         font_provider,
         font_handle,
         font_texture_cache,
+        tinted_font_texture_cache: FontTextureCache::new_tinted(),
 
         quit_requested: false,
         copy_requested: false,
+        color_copied: false,
+        selection_printed: false,
     };
 
-    app.init_all_screens()?;
-    app.capture_all_screens()?;
+    app.init_all_screens();
+    app.capture_all_screens(true)?;
     app.overlay_all_screens()?;
 
     loop {
         if app.quit_requested || app.clipboard.cancelled {
             break;
         }
-
-        unsafe {
-            (app.conn.libs.wayland.wl_display_dispatch)(app.conn.libs.wayland_display.as_ptr());
+        if app.copy_requested && app.config.detach {
+            let (conn, mut clipboard) = app.into_clipboard();
+            // NOTE: every format is encoded before forking so that the child does not hold on to
+            // the pixels for as long as it serves the clipboard.
+            clipboard.encode_all()?;
+            // NOTE: the gl driver does not survive fork, the child must not inherit it.
+            unsafe { conn.libs.egl_context.terminate() };
+            detach()?;
+
+            serve_clipboard(&conn, &clipboard);
+            unsafe { libc::_exit(0) }
         }
 
+        // NOTE: waiting for events is cut short when a held key is due to repeat.
+        dispatch_timeout(&app.conn, app.input.key_repeat_timeout());
+        app.input.repeat_key();
+
         if app.copy_requested {
             continue;
         }
 
+        app.sync_screens()?;
+        app.sync_input();
         app.update()?;
-        app.draw(ScreenDrawOpts::default())?;
+        app.draw()?;
+    }
+
+    if app.config.select && !app.selection_printed {
+        return Err(anyhow!("selection cancelled"));
     }
 
     Ok(())
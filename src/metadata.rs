@@ -0,0 +1,135 @@
+use std::time::Duration;
+
+use crate::{
+    encoder::ImageMetadata,
+    geometry,
+    gfx::{Rect, Size, Vec2},
+};
+
+/// describes where a capture comes from.
+pub struct CaptureMetadata<'a> {
+    /// names of captured outputs.
+    pub outputs: &'a [String],
+    /// in global compositor coordinates.
+    pub geometry: Rect,
+    /// of the image, in physical pixels.
+    pub size: Size,
+    /// since unix epoch.
+    pub time: Duration,
+    /// pixel density of the output that covers the most of the capture, in its own pixels.
+    pub output_pixels_per_meter: Option<Vec2>,
+    /// physical pixels per logical pixel of that output.
+    pub output_scale: f32,
+}
+
+/// formats as rfc 3339 in utc with milliseconds.
+fn format_time(time: Duration) -> String {
+    let secs = time.as_secs() as libc::time_t;
+    let mut tm: libc::tm = unsafe { std::mem::zeroed() };
+    if unsafe { libc::gmtime_r(&secs, &mut tm) }.is_null() {
+        return format!("{}", time.as_secs());
+    }
+    format!(
+        "{:04}-{:02}-{:02}T{:02}:{:02}:{:02}.{:03}Z",
+        tm.tm_year + 1900,
+        tm.tm_mon + 1,
+        tm.tm_mday,
+        tm.tm_hour,
+        tm.tm_min,
+        tm.tm_sec,
+        time.subsec_millis()
+    )
+}
+
+/// formats without trailing zeroes, e.g. 1.25 or 2.
+pub fn format_scale(scale: f32) -> String {
+    format!("{}", (scale * 1000.0).round() / 1000.0)
+}
+
+impl CaptureMetadata<'_> {
+    /// physical pixels per logical pixel of the image.
+    fn scale(&self) -> f32 {
+        self.size.width as f32 / self.geometry.width()
+    }
+
+    pub fn to_image_metadata(&self) -> ImageMetadata {
+        let scale = self.scale();
+        let physical_geometry = Rect::new(
+            self.geometry.min * scale,
+            self.geometry.min * scale + self.size.as_vec2(),
+        );
+
+        let mut text = vec![
+            ("Software".to_string(), "bscreen".to_string()),
+            ("Creation Time".to_string(), format_time(self.time)),
+        ];
+        if !self.outputs.is_empty() {
+            text.push(("Source".to_string(), self.outputs.join(", ")));
+        }
+        text.push((
+            "bscreen:geometry".to_string(),
+            geometry::format(&self.geometry),
+        ));
+        text.push((
+            "bscreen:physical-geometry".to_string(),
+            geometry::format(&physical_geometry),
+        ));
+        text.push(("bscreen:scale".to_string(), format_scale(scale)));
+
+        // NOTE: the output's pixels may have been resampled when stitched.
+        let pixels_per_meter = self.output_pixels_per_meter.map(|output_pixels_per_meter| {
+            let ppm = output_pixels_per_meter * (scale / self.output_scale);
+            (ppm.x.round() as u32, ppm.y.round() as u32)
+        });
+
+        ImageMetadata {
+            text,
+            pixels_per_meter,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_format() {
+        assert_eq!(format_time(Duration::ZERO), "1970-01-01T00:00:00.000Z");
+        assert_eq!(
+            format_time(Duration::from_millis(1_700_000_000_123)),
+            "2023-11-14T22:13:20.123Z"
+        );
+        assert_eq!(format_scale(1.0), "1");
+        assert_eq!(format_scale(1.25), "1.25");
+        assert_eq!(format_scale(4.0 / 3.0), "1.333");
+    }
+
+    #[test]
+    fn test_to_image_metadata() {
+        let outputs = ["DP-1".to_string()];
+        let metadata = CaptureMetadata {
+            outputs: &outputs,
+            geometry: Rect::new(Vec2::new(10.0, 20.0), Vec2::new(110.0, 70.0)),
+            size: Size::new(200, 100),
+            time: Duration::ZERO,
+            output_pixels_per_meter: Some(Vec2::new(4000.0, 4000.0)),
+            output_scale: 1.0,
+        }
+        .to_image_metadata();
+
+        let text = |keyword: &str| {
+            metadata
+                .text
+                .iter()
+                .find(|(k, _)| k == keyword)
+                .map(|(_, v)| v.as_str())
+        };
+        assert_eq!(text("Source"), Some("DP-1"));
+        assert_eq!(text("bscreen:geometry"), Some("10,20 100x50"));
+        assert_eq!(text("bscreen:physical-geometry"), Some("20,40 200x100"));
+        assert_eq!(text("bscreen:scale"), Some("2"));
+        // upscaled twice.
+        assert_eq!(metadata.pixels_per_meter, Some((8000, 8000)));
+    }
+}
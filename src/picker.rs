@@ -0,0 +1,189 @@
+use std::collections::VecDeque;
+
+use anyhow::anyhow;
+
+use crate::{
+    gfx::{DrawBuffer, Rect, Rgba8, Vec2},
+    label::{self, Label, LabelDrawData},
+};
+
+pub mod theme {
+    pub const MAX_PICKS: usize = 5;
+}
+
+/// text representation of picked colors.
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
+pub enum ColorFormat {
+    /// #1e90ff
+    #[default]
+    Hex,
+    /// rgb(30, 144, 255)
+    Rgb,
+    /// hsl(210, 100%, 56%)
+    Hsl,
+    /// (0.118, 0.565, 1.000)
+    Float,
+}
+
+impl std::str::FromStr for ColorFormat {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "hex" => Ok(Self::Hex),
+            "rgb" => Ok(Self::Rgb),
+            "hsl" => Ok(Self::Hsl),
+            "float" => Ok(Self::Float),
+            _ => Err(anyhow!(
+                "invalid color format {s}, expected hex, rgb, hsl or float"
+            )),
+        }
+    }
+}
+
+/// hue in degrees, saturation and lightness in 0..=1.
+fn hsl(color: Rgba8) -> (f32, f32, f32) {
+    let r = color.r as f32 / 255.0;
+    let g = color.g as f32 / 255.0;
+    let b = color.b as f32 / 255.0;
+    let max = r.max(g).max(b);
+    let min = r.min(g).min(b);
+    let l = (max + min) / 2.0;
+    let d = max - min;
+    if d == 0.0 {
+        return (0.0, 0.0, l);
+    }
+
+    let s = d / (1.0 - (2.0 * l - 1.0).abs());
+    let h = if max == r {
+        (g - b) / d
+    } else if max == g {
+        (b - r) / d + 2.0
+    } else {
+        (r - g) / d + 4.0
+    };
+    ((h * 60.0).rem_euclid(360.0), s, l)
+}
+
+impl ColorFormat {
+    pub fn format(&self, color: Rgba8) -> String {
+        match self {
+            Self::Hex => format!("#{:02x}{:02x}{:02x}", color.r, color.g, color.b),
+            Self::Rgb => format!("rgb({}, {}, {})", color.r, color.g, color.b),
+            Self::Hsl => {
+                let (h, s, l) = hsl(color);
+                format!(
+                    "hsl({}, {}%, {}%)",
+                    h.round() as u32 % 360,
+                    (s * 100.0).round(),
+                    (l * 100.0).round()
+                )
+            }
+            Self::Float => format!(
+                "({:.3}, {:.3}, {:.3})",
+                color.r as f32 / 255.0,
+                color.g as f32 / 255.0,
+                color.b as f32 / 255.0
+            ),
+        }
+    }
+}
+
+// NOTE: similarly to crop picker operates in global compositor coordinates.
+pub struct Picker {
+    pub active: bool,
+    format: ColorFormat,
+    /// position of the pointer and color of the pixel under it.
+    pub hovered: Option<(Vec2, Rgba8)>,
+    /// most recent first.
+    picks: VecDeque<Rgba8>,
+    label: Label,
+}
+
+impl Picker {
+    pub fn new(active: bool, format: ColorFormat) -> Self {
+        Self {
+            active,
+            format,
+            hovered: None,
+            picks: VecDeque::new(),
+            label: Label::default(),
+        }
+    }
+
+    pub fn toggle(&mut self) {
+        self.active = !self.active;
+        self.hovered = None;
+    }
+
+    /// remembers the hovered color and returns it.
+    pub fn pick(&mut self) -> Option<Rgba8> {
+        let (_, color) = self.hovered?;
+        self.picks.retain(|pick| *pick != color);
+        self.picks.push_front(color);
+        self.picks.truncate(theme::MAX_PICKS);
+        Some(color)
+    }
+
+    /// draws recent picks in the bottom left corner of the screen that has the pointer.
+    ///
+    /// NOTE: the hovered color is shown by the loupe.
+    pub fn draw(
+        &mut self,
+        draw_buffer: &mut DrawBuffer,
+        screen_rect: Rect,
+        data: &mut LabelDrawData,
+    ) {
+        let Some((position, _)) = self.hovered else {
+            return;
+        };
+        if !self.active || !screen_rect.contains(&position) {
+            return;
+        }
+        let view_size = screen_rect.size();
+
+        let mut max_y = view_size.y - label::theme::PADDING;
+        for pick in self.picks.iter() {
+            let size = self
+                .label
+                .layout(&self.format.format(*pick), Some(*pick), data);
+            let min = Vec2::new(label::theme::PADDING, max_y - size.y);
+            self.label
+                .draw(draw_buffer, Rect::new(min, min + size), data);
+            max_y = min.y - label::theme::PADDING;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_format() {
+        let color = Rgba8::new(30, 144, 255, 255);
+        assert_eq!(ColorFormat::Hex.format(color), "#1e90ff");
+        assert_eq!(ColorFormat::Rgb.format(color), "rgb(30, 144, 255)");
+        assert_eq!(ColorFormat::Hsl.format(color), "hsl(210, 100%, 56%)");
+        assert_eq!(ColorFormat::Float.format(color), "(0.118, 0.565, 1.000)");
+
+        assert_eq!(ColorFormat::Hsl.format(Rgba8::WHITE), "hsl(0, 0%, 100%)");
+        assert_eq!(
+            ColorFormat::Hsl.format(Rgba8::new(255, 0, 128, 255)),
+            "hsl(330, 100%, 50%)"
+        );
+    }
+
+    #[test]
+    fn test_pick() {
+        let mut picker = Picker::new(true, ColorFormat::Hex);
+        assert_eq!(picker.pick(), None);
+
+        for color in [Rgba8::WHITE, Rgba8::BLACK, Rgba8::WHITE] {
+            picker.hovered = Some((Vec2::ZERO, color));
+            assert_eq!(picker.pick(), Some(color));
+        }
+        // NOTE: repeated picks are moved to the front.
+        assert_eq!(picker.picks, [Rgba8::WHITE, Rgba8::BLACK]);
+    }
+}
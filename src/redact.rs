@@ -0,0 +1,315 @@
+use anyhow::anyhow;
+
+use crate::gfx::{DrawBuffer, Rect, Size, TextureTransform, Vec2};
+
+pub mod theme {
+    /// in logical pixels, also the size of blocks that blurring starts with.
+    pub const BLUR_RADIUS: f32 = 8.0;
+    pub const DEFAULT_BLOCK_SIZE: u32 = 12;
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum RedactMode {
+    Pixelate,
+    Blur,
+}
+
+/// in global compositor coordinates, normalized.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Redaction {
+    pub rect: Rect,
+    pub mode: RedactMode,
+}
+
+pub fn parse_block_size(value: &str) -> anyhow::Result<u32> {
+    match value.parse::<u32>() {
+        Ok(block_size) if (2..=256).contains(&block_size) => Ok(block_size),
+        _ => Err(anyhow!("invalid block size {value}, expected 2..=256")),
+    }
+}
+
+impl Redaction {
+    /// logical size of blocks the rect is divided into.
+    fn block_size(&self, pixelate_block_size: u32) -> f32 {
+        match self.mode {
+            RedactMode::Pixelate => pixelate_block_size as f32,
+            RedactMode::Blur => theme::BLUR_RADIUS,
+        }
+    }
+
+    /// approximates the redaction with blocks of the screen texture. view rect is the rect of the
+    /// screen in global compositor coordinates, drawing happens relative to it.
+    ///
+    /// NOTE: blur is previewed as the pixelation it starts with.
+    pub fn draw_preview(
+        &self,
+        draw_buffer: &mut DrawBuffer,
+        view_rect: Rect,
+        pixelate_block_size: u32,
+        texture_handle: u32,
+        texture_transform: TextureTransform,
+    ) {
+        if !self.rect.intersects(&view_rect) {
+            return;
+        }
+        let rect = self.rect.constrain_to(&view_rect);
+        let block_size = self.block_size(pixelate_block_size);
+        let view_size = view_rect.size();
+
+        // NOTE: blocks are anchored to the redaction rect, not to the clipped one, so that they
+        // line up across screens.
+        let first = |min: f32, clipped_min: f32| {
+            min + ((clipped_min - min) / block_size).floor() * block_size
+        };
+        let mut y = first(self.rect.min.y, rect.min.y);
+        while y < rect.max.y {
+            let mut x = first(self.rect.min.x, rect.min.x);
+            while x < rect.max.x {
+                let min = Vec2::new(x, y);
+                let block = Rect::new(min, min + Vec2::splat(block_size)).constrain_to(&rect);
+                let center = ((block.min + block.max) * 0.5 - view_rect.min) / view_size;
+                draw_buffer.push_rect_textured(
+                    block.translate(&(Vec2::ZERO - view_rect.min)),
+                    texture_handle,
+                    Rect::new(center, center),
+                    texture_transform,
+                );
+                x += block_size;
+            }
+            y += block_size;
+        }
+    }
+
+    /// redacts pixels of an image of region in place. scale converts logical pixels into pixels
+    /// of the image.
+    pub fn apply(
+        &self,
+        pixels: &mut [u8],
+        size: Size,
+        region: Rect,
+        scale: f32,
+        pixelate_block_size: u32,
+    ) {
+        if !self.rect.intersects(&region) {
+            return;
+        }
+        let rect = self.rect.constrain_to(&region);
+        let to_pixels =
+            |value: f32, max: u32| (value * scale).round().clamp(0.0, max as f32) as u32;
+        let bounds = PixelBounds {
+            x0: to_pixels(rect.min.x - region.min.x, size.width),
+            y0: to_pixels(rect.min.y - region.min.y, size.height),
+            x1: to_pixels(rect.max.x - region.min.x, size.width),
+            y1: to_pixels(rect.max.y - region.min.y, size.height),
+        };
+        if bounds.x0 >= bounds.x1 || bounds.y0 >= bounds.y1 {
+            return;
+        }
+
+        // NOTE: blocks are anchored to the redaction rect, not to the clipped one, same as in
+        // draw_preview.
+        let origin = (
+            ((self.rect.min.x - region.min.x) * scale).round() as i64,
+            ((self.rect.min.y - region.min.y) * scale).round() as i64,
+        );
+        let block_size = (self.block_size(pixelate_block_size) * scale)
+            .round()
+            .max(1.0) as u32;
+        pixelate(pixels, size, &bounds, origin, block_size);
+        // NOTE: blur alone can be partially undone with deconvolution, averaging blocks first
+        // ensures that the original pixels are gone.
+        if self.mode == RedactMode::Blur {
+            blur(pixels, size, &bounds, block_size);
+        }
+    }
+}
+
+/// half-open pixel ranges.
+#[derive(Debug)]
+struct PixelBounds {
+    x0: u32,
+    y0: u32,
+    x1: u32,
+    y1: u32,
+}
+
+/// origin is the pixel that the grid of blocks starts at, it may be outside of bounds.
+fn pixelate(
+    pixels: &mut [u8],
+    size: Size,
+    bounds: &PixelBounds,
+    origin: (i64, i64),
+    block_size: u32,
+) {
+    let stride = size.width as usize * 4;
+    let step = block_size as i64;
+    // NOTE: blocks start at the first one that reaches into bounds and are clipped to them.
+    let blocks = |origin: i64, min: u32, max: u32| {
+        let first = origin + (min as i64 - origin).div_euclid(step) * step;
+        (first..max as i64)
+            .step_by(block_size as usize)
+            .map(move |start| {
+                let end = start + step;
+                (start.max(min as i64) as u32, end.min(max as i64) as u32)
+            })
+    };
+    for (by, by1) in blocks(origin.1, bounds.y0, bounds.y1) {
+        for (bx, bx1) in blocks(origin.0, bounds.x0, bounds.x1) {
+            let mut sum = [0u32; 4];
+            for y in by..by1 {
+                for x in bx..bx1 {
+                    let offset = y as usize * stride + x as usize * 4;
+                    for c in 0..4 {
+                        sum[c] += pixels[offset + c] as u32;
+                    }
+                }
+            }
+            let count = (by1 - by) * (bx1 - bx);
+            let average = sum.map(|sum| ((sum + count / 2) / count) as u8);
+
+            for y in by..by1 {
+                for x in bx..bx1 {
+                    let offset = y as usize * stride + x as usize * 4;
+                    pixels[offset..offset + 4].copy_from_slice(&average);
+                }
+            }
+        }
+    }
+}
+
+fn gaussian_kernel(radius: u32) -> Vec<f32> {
+    // NOTE: 3 sigmas cover nearly all of the bell.
+    let sigma = radius as f32 / 3.0;
+    let mut kernel: Vec<f32> = (-(radius as i32)..=radius as i32)
+        .map(|i| (-(i * i) as f32 / (2.0 * sigma * sigma)).exp())
+        .collect();
+    let sum: f32 = kernel.iter().sum();
+    kernel.iter_mut().for_each(|weight| *weight /= sum);
+    kernel
+}
+
+/// separable gaussian blur that only samples pixels within bounds, so that nothing around the
+/// redacted rect bleeds into it.
+fn blur(pixels: &mut [u8], size: Size, bounds: &PixelBounds, radius: u32) {
+    let kernel = gaussian_kernel(radius);
+    let stride = size.width as usize * 4;
+    let width = (bounds.x1 - bounds.x0) as usize;
+    let height = (bounds.y1 - bounds.y0) as usize;
+    let offset =
+        |x: usize, y: usize| (bounds.y0 as usize + y) * stride + (bounds.x0 as usize + x) * 4;
+
+    // horizontal pass into a temporary buffer, vertical pass back into pixels.
+    let mut horizontal = vec![0.0f32; width * height * 4];
+    for y in 0..height {
+        for x in 0..width {
+            let mut acc = [0.0f32; 4];
+            for (k, weight) in kernel.iter().enumerate() {
+                let sx = (x as i64 + k as i64 - radius as i64).clamp(0, width as i64 - 1) as usize;
+                let src = offset(sx, y);
+                for c in 0..4 {
+                    acc[c] += pixels[src + c] as f32 * weight;
+                }
+            }
+            horizontal[(y * width + x) * 4..(y * width + x) * 4 + 4].copy_from_slice(&acc);
+        }
+    }
+    for y in 0..height {
+        for x in 0..width {
+            let mut acc = [0.0f32; 4];
+            for (k, weight) in kernel.iter().enumerate() {
+                let sy = (y as i64 + k as i64 - radius as i64).clamp(0, height as i64 - 1) as usize;
+                for c in 0..4 {
+                    acc[c] += horizontal[(sy * width + x) * 4 + c] * weight;
+                }
+            }
+            let dst = offset(x, y);
+            for c in 0..4 {
+                pixels[dst + c] = acc[c].round().clamp(0.0, 255.0) as u8;
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn gradient(size: Size) -> Vec<u8> {
+        let mut pixels = Vec::new();
+        for y in 0..size.height {
+            for x in 0..size.width {
+                pixels.extend_from_slice(&[(x * 16) as u8, (y * 16) as u8, 0, 255]);
+            }
+        }
+        pixels
+    }
+
+    #[test]
+    fn test_pixelate() {
+        let size = Size::new(8, 8);
+        let mut pixels = gradient(size);
+        let redaction = Redaction {
+            rect: Rect::new(Vec2::new(1.0, 1.0), Vec2::new(3.0, 3.0)),
+            mode: RedactMode::Pixelate,
+        };
+        // NOTE: scale 2 makes it pixels 2..6 with blocks of 4.
+        let region = Rect::new(Vec2::ZERO, Vec2::new(4.0, 4.0));
+        redaction.apply(&mut pixels, size, region, 2.0, 2);
+
+        let pixel = |x: usize, y: usize| &pixels[(y * 8 + x) * 4..(y * 8 + x) * 4 + 4];
+        for y in 2..6 {
+            for x in 2..6 {
+                assert_eq!(pixel(x, y), [56, 56, 0, 255]);
+            }
+        }
+        // NOTE: pixels outside stay the same.
+        assert_eq!(pixel(1, 1), [16, 16, 0, 255]);
+        assert_eq!(pixel(6, 6), [96, 96, 0, 255]);
+    }
+
+    #[test]
+    fn test_blur_stays_within_bounds() {
+        let size = Size::new(32, 32);
+        let original = gradient(size);
+        let mut pixels = original.clone();
+        let redaction = Redaction {
+            rect: Rect::new(Vec2::new(4.0, 4.0), Vec2::new(20.0, 12.0)),
+            mode: RedactMode::Blur,
+        };
+        let region = Rect::new(Vec2::ZERO, Vec2::new(32.0, 32.0));
+        redaction.apply(&mut pixels, size, region, 1.0, 2);
+
+        for y in 0..32 {
+            for x in 0..32 {
+                let offset = (y * 32 + x) * 4;
+                let inside = (4..20).contains(&x) && (4..12).contains(&y);
+                if !inside {
+                    assert_eq!(pixels[offset..offset + 4], original[offset..offset + 4]);
+                } else {
+                    assert_eq!(pixels[offset + 3], 255);
+                }
+            }
+        }
+        assert_ne!(pixels, original);
+    }
+
+    #[test]
+    fn test_pixelate_clipped_by_region() {
+        let size = Size::new(6, 6);
+        let mut pixels = gradient(size);
+        let redaction = Redaction {
+            rect: Rect::new(Vec2::ZERO, Vec2::new(8.0, 8.0)),
+            mode: RedactMode::Pixelate,
+        };
+        // NOTE: blocks start at the redaction rect, the region cuts the first one to pixels 0..2.
+        let region = Rect::new(Vec2::new(2.0, 2.0), Vec2::new(8.0, 8.0));
+        redaction.apply(&mut pixels, size, region, 1.0, 4);
+
+        let pixel = |x: usize, y: usize| &pixels[(y * 6 + x) * 4..(y * 6 + x) * 4 + 4];
+        assert_eq!(pixel(0, 0), [8, 8, 0, 255]);
+        assert_eq!(pixel(1, 1), [8, 8, 0, 255]);
+        assert_eq!(pixel(2, 2), [56, 56, 0, 255]);
+        assert_eq!(pixel(5, 5), [56, 56, 0, 255]);
+        assert_eq!(pixel(1, 5), [8, 56, 0, 255]);
+    }
+}
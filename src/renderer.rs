@@ -1,7 +1,7 @@
 use std::mem::offset_of;
 
 use crate::{
-    gfx::{DrawBuffer, Size, TextureFormat, Vertex},
+    gfx::{DrawBuffer, Rect, Size, TextureFormat, Vec2, Vertex},
     gl,
 };
 
@@ -148,4 +148,26 @@ impl Renderer {
             );
         }
     }
+
+    /// draws into an offscreen framebuffer of the given physical size and reads back rgba8
+    /// pixels; this does not need a window surface.
+    pub unsafe fn draw_offscreen(
+        &self,
+        size: Size,
+        draw_buffer: &DrawBuffer,
+    ) -> anyhow::Result<Vec<u8>> {
+        let framebuffer = gl::Framebuffer::new(self.gl_lib, size.width, size.height)?;
+
+        self.gl_lib.ClearColor(0.0, 0.0, 0.0, 0.0);
+        self.gl_lib.Clear(gl::sys::COLOR_BUFFER_BIT);
+        self.draw(size, 1.0, draw_buffer);
+
+        let read_rect = Rect::new(Vec2::ZERO, size.as_vec2());
+        let pixels = gl::read_pixels(self.gl_lib, read_rect, size);
+
+        self.gl_lib.BindFramebuffer(gl::sys::FRAMEBUFFER, 0);
+        drop(framebuffer);
+
+        Ok(pixels)
+    }
 }
@@ -0,0 +1,307 @@
+use std::f32::consts::PI;
+
+use anyhow::anyhow;
+
+use crate::gfx::{Size, Vec2};
+
+/// defines the size of exported images.
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
+pub enum ExportScale {
+    /// pixels of the capture as they are.
+    #[default]
+    Physical,
+    /// one pixel per logical pixel.
+    Logical,
+    /// relative to logical pixels, e.g. 2 is twice the logical size.
+    Factor(f32),
+    /// physical, but downscaled so that neither side exceeds the given number of pixels.
+    MaxDimension(u32),
+}
+
+impl std::str::FromStr for ExportScale {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let invalid = || {
+            anyhow!("invalid export scale {s}, expected physical, logical, <factor>x or <pixels>px")
+        };
+        match s {
+            "physical" => Ok(Self::Physical),
+            "logical" => Ok(Self::Logical),
+            _ => {
+                if let Some(pixels) = s.strip_suffix("px") {
+                    match pixels.parse::<u32>() {
+                        Ok(pixels) if pixels > 0 => Ok(Self::MaxDimension(pixels)),
+                        _ => Err(invalid()),
+                    }
+                } else {
+                    match s.strip_suffix('x').unwrap_or(s).parse::<f32>() {
+                        Ok(factor) if factor.is_finite() && factor > 0.0 => {
+                            Ok(Self::Factor(factor))
+                        }
+                        _ => Err(invalid()),
+                    }
+                }
+            }
+        }
+    }
+}
+
+impl ExportScale {
+    /// `logical_size` is the size of the captured region in logical pixels, `size` is the size of
+    /// the capture.
+    pub fn apply(&self, logical_size: Vec2, size: Size) -> Size {
+        let scaled = |size: Vec2| {
+            Size::new(
+                (size.x.round() as u32).max(1),
+                (size.y.round() as u32).max(1),
+            )
+        };
+        match *self {
+            Self::Physical => size,
+            Self::Logical => scaled(logical_size),
+            Self::Factor(factor) => scaled(logical_size * factor),
+            Self::MaxDimension(max) => {
+                let longest = size.width.max(size.height);
+                if longest <= max {
+                    size
+                } else {
+                    scaled(size.as_vec2() * (max as f32 / longest as f32))
+                }
+            }
+        }
+    }
+}
+
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
+pub enum ResampleFilter {
+    /// sharpest, may ring around high contrast edges (e.g. text).
+    #[default]
+    Lanczos,
+    /// softer, without ringing.
+    Mitchell,
+}
+
+impl std::str::FromStr for ResampleFilter {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "lanczos" => Ok(Self::Lanczos),
+            "mitchell" => Ok(Self::Mitchell),
+            _ => Err(anyhow!(
+                "invalid resample filter {s}, expected lanczos or mitchell"
+            )),
+        }
+    }
+}
+
+fn sinc(x: f32) -> f32 {
+    if x == 0.0 {
+        1.0
+    } else {
+        let x = x * PI;
+        x.sin() / x
+    }
+}
+
+impl ResampleFilter {
+    /// radius of the filter in source pixels (when not downscaling).
+    fn support(&self) -> f32 {
+        match self {
+            Self::Lanczos => 3.0,
+            Self::Mitchell => 2.0,
+        }
+    }
+
+    fn weight(&self, x: f32) -> f32 {
+        let x = x.abs();
+        match self {
+            Self::Lanczos => {
+                if x < 3.0 {
+                    sinc(x) * sinc(x / 3.0)
+                } else {
+                    0.0
+                }
+            }
+            // NOTE: b = c = 1/3, as recommended by mitchell and netravali.
+            Self::Mitchell => {
+                const B: f32 = 1.0 / 3.0;
+                const C: f32 = 1.0 / 3.0;
+                if x < 1.0 {
+                    ((12.0 - 9.0 * B - 6.0 * C) * x * x * x
+                        + (-18.0 + 12.0 * B + 6.0 * C) * x * x
+                        + (6.0 - 2.0 * B))
+                        / 6.0
+                } else if x < 2.0 {
+                    ((-B - 6.0 * C) * x * x * x
+                        + (6.0 * B + 30.0 * C) * x * x
+                        + (-12.0 * B - 48.0 * C) * x
+                        + (8.0 * B + 24.0 * C))
+                        / 6.0
+                } else {
+                    0.0
+                }
+            }
+        }
+    }
+}
+
+/// contributions of source pixels to a destination pixel along one axis.
+struct Contribution {
+    start: usize,
+    weights: Vec<f32>,
+}
+
+fn contributions(filter: ResampleFilter, src_len: u32, dst_len: u32) -> Vec<Contribution> {
+    let ratio = src_len as f32 / dst_len as f32;
+    // NOTE: when downscaling the filter is stretched so that every source pixel contributes.
+    let filter_scale = ratio.max(1.0);
+    let support = filter.support() * filter_scale;
+
+    (0..dst_len)
+        .map(|i| {
+            let center = (i as f32 + 0.5) * ratio;
+            let start = ((center - support).floor() as i64).max(0) as usize;
+            let end = ((center + support).ceil() as i64).min(src_len as i64) as usize;
+
+            let mut weights: Vec<f32> = (start..end)
+                .map(|j| filter.weight((j as f32 + 0.5 - center) / filter_scale))
+                .collect();
+            let sum: f32 = weights.iter().sum();
+            if sum != 0.0 {
+                weights.iter_mut().for_each(|weight| *weight /= sum);
+            }
+
+            Contribution { start, weights }
+        })
+        .collect()
+}
+
+/// resamples tightly packed rgba8 pixels. channels are filtered independently, which is correct
+/// for premultiplied alpha (what offscreen drawing produces) and for opaque pixels.
+pub fn resample(pixels: &[u8], size: Size, new_size: Size, filter: ResampleFilter) -> Vec<u8> {
+    assert_eq!(pixels.len(), size.width as usize * size.height as usize * 4);
+    if size == new_size {
+        return pixels.to_vec();
+    }
+
+    let (src_width, src_height) = (size.width as usize, size.height as usize);
+    let (dst_width, dst_height) = (new_size.width as usize, new_size.height as usize);
+
+    // horizontal pass, src_height rows of dst_width pixels.
+    let mut horizontal = vec![0.0f32; dst_width * src_height * 4];
+    let columns = contributions(filter, size.width, new_size.width);
+    for y in 0..src_height {
+        let src_row = &pixels[y * src_width * 4..(y + 1) * src_width * 4];
+        let dst_row = &mut horizontal[y * dst_width * 4..(y + 1) * dst_width * 4];
+        for (x, contribution) in columns.iter().enumerate() {
+            let mut acc = [0.0f32; 4];
+            for (k, weight) in contribution.weights.iter().enumerate() {
+                let offset = (contribution.start + k) * 4;
+                for c in 0..4 {
+                    acc[c] += src_row[offset + c] as f32 * weight;
+                }
+            }
+            dst_row[x * 4..x * 4 + 4].copy_from_slice(&acc);
+        }
+    }
+
+    // vertical pass.
+    let mut resampled = vec![0u8; dst_width * dst_height * 4];
+    let rows = contributions(filter, size.height, new_size.height);
+    for (y, contribution) in rows.iter().enumerate() {
+        let dst_row = &mut resampled[y * dst_width * 4..(y + 1) * dst_width * 4];
+        for x in 0..dst_width * 4 {
+            let mut acc = 0.0;
+            for (k, weight) in contribution.weights.iter().enumerate() {
+                acc += horizontal[(contribution.start + k) * dst_width * 4 + x] * weight;
+            }
+            dst_row[x] = acc.round().clamp(0.0, 255.0) as u8;
+        }
+    }
+
+    resampled
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_export_scale() {
+        assert_eq!(
+            "logical".parse::<ExportScale>().unwrap(),
+            ExportScale::Logical
+        );
+        assert_eq!(
+            "1.5x".parse::<ExportScale>().unwrap(),
+            ExportScale::Factor(1.5)
+        );
+        assert_eq!(
+            "2".parse::<ExportScale>().unwrap(),
+            ExportScale::Factor(2.0)
+        );
+        assert_eq!(
+            "1920px".parse::<ExportScale>().unwrap(),
+            ExportScale::MaxDimension(1920)
+        );
+        assert!("0x".parse::<ExportScale>().is_err());
+        assert!("0px".parse::<ExportScale>().is_err());
+        assert!("big".parse::<ExportScale>().is_err());
+    }
+
+    #[test]
+    fn test_export_scale_apply() {
+        let logical_size = Vec2::new(1000.0, 500.0);
+        let size = Size::new(2000, 1000);
+        assert_eq!(ExportScale::Physical.apply(logical_size, size), size);
+        assert_eq!(
+            ExportScale::Logical.apply(logical_size, size),
+            Size::new(1000, 500)
+        );
+        assert_eq!(
+            ExportScale::Factor(1.5).apply(logical_size, size),
+            Size::new(1500, 750)
+        );
+        assert_eq!(
+            ExportScale::MaxDimension(800).apply(logical_size, size),
+            Size::new(800, 400)
+        );
+        // never upscales.
+        assert_eq!(
+            ExportScale::MaxDimension(4000).apply(logical_size, size),
+            size
+        );
+    }
+
+    #[test]
+    fn test_resample_uniform() {
+        for filter in [ResampleFilter::Lanczos, ResampleFilter::Mitchell] {
+            let pixels = [10, 20, 30, 255].repeat(7 * 5);
+            let resampled = resample(&pixels, Size::new(7, 5), Size::new(3, 2), filter);
+            assert_eq!(resampled, [10, 20, 30, 255].repeat(3 * 2));
+
+            let resampled = resample(&pixels, Size::new(7, 5), Size::new(11, 9), filter);
+            assert_eq!(resampled, [10, 20, 30, 255].repeat(11 * 9));
+        }
+    }
+
+    #[test]
+    fn test_resample_halves_checkerboard() {
+        // NOTE: 2x2 blocks of black and white average out to gray when halved.
+        let size = Size::new(8, 8);
+        let mut pixels = Vec::new();
+        for y in 0..size.height {
+            for x in 0..size.width {
+                let value = if (x / 2 + y / 2) % 2 == 0 { 0 } else { 255 };
+                pixels.extend_from_slice(&[value, value, value, 255]);
+            }
+        }
+        let resampled = resample(&pixels, size, Size::new(2, 2), ResampleFilter::Mitchell);
+        for pixel in resampled.chunks_exact(4) {
+            assert!((100..=155).contains(&pixel[0]), "{pixel:?}");
+            assert_eq!(pixel[3], 255);
+        }
+    }
+}
@@ -0,0 +1,448 @@
+use anyhow::anyhow;
+
+use crate::{
+    gfx::{DrawBuffer, Rect, RectFill, Rgba8, Size, TextureTransform, Vec2},
+    gl,
+};
+
+/// fills parts of outputs that their pixels do not cover in native mode.
+const GAP_BG: Rgba8 = Rgba8::BLACK;
+
+/// defines how outputs with different scales are combined into a single image.
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
+pub enum StitchScale {
+    /// every output is upscaled to the density of the output with the highest scale.
+    #[default]
+    Max,
+    /// every output keeps its own pixels. each span of the image is as dense as the densest output
+    /// that covers it, thus outputs next to denser ones do not fill their part of the image; the
+    /// rest of it is black.
+    Native,
+}
+
+impl std::str::FromStr for StitchScale {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "max" => Ok(Self::Max),
+            "native" => Ok(Self::Native),
+            _ => Err(anyhow!("invalid stitch scale {s}, expected max or native")),
+        }
+    }
+}
+
+pub struct StitchSource {
+    /// in global compositor coordinates.
+    pub logical_rect: Rect,
+    /// size of the capture as it appears on the output, transform is already accounted for.
+    pub size: Size,
+    pub texture_handle: gl::sys::types::GLuint,
+    pub texture_transform: TextureTransform,
+}
+
+impl StitchSource {
+    fn scale(&self) -> f32 {
+        self.size.width as f32 / self.logical_rect.width()
+    }
+
+    fn scale_y(&self) -> f32 {
+        self.size.height as f32 / self.logical_rect.height()
+    }
+}
+
+fn round(v: Vec2) -> Vec2 {
+    Vec2::new(v.x.round(), v.y.round())
+}
+
+/// maps logical coordinates within min..max along an axis to pixels so that each span is as dense
+/// as the densest of spans (logical min, logical max and scale of sources) that cover it.
+fn native_axis(min: f32, max: f32, spans: &[(f32, f32, f32)]) -> Vec<(f32, f32)> {
+    let mut edges: Vec<f32> = spans
+        .iter()
+        .flat_map(|&(span_min, span_max, _)| [span_min, span_max])
+        .chain([min, max])
+        .map(|edge| edge.clamp(min, max))
+        .collect();
+    edges.sort_by(f32::total_cmp);
+    edges.dedup();
+
+    let mut pixel = 0.0;
+    let mut mapping = vec![(edges[0], pixel)];
+    for pair in edges.windows(2) {
+        let scale = spans
+            .iter()
+            .filter(|&&(span_min, span_max, _)| span_min < pair[1] && span_max > pair[0])
+            .map(|&(_, _, scale)| scale)
+            .fold(1.0, f32::max);
+        pixel += ((pair[1] - pair[0]) * scale).round();
+        mapping.push((pair[1], pixel));
+    }
+    mapping
+}
+
+/// pixel of a logical coordinate that is one of the edges of the mapping.
+fn native_pixel(mapping: &[(f32, f32)], logical: f32) -> f32 {
+    let idx = mapping.partition_point(|(edge, _)| *edge < logical);
+    mapping[idx.min(mapping.len() - 1)].1
+}
+
+/// where sources end up in the stitched image.
+#[derive(Debug, PartialEq)]
+pub struct StitchLayout {
+    pub size: Size,
+    /// rects of each of the sources within the image.
+    pub dst_rects: Vec<Rect>,
+    /// parts of the image that belong to each of the sources, larger than their dst rects in
+    /// native mode when sources are next to denser ones.
+    cells: Vec<Rect>,
+    /// parts of dst rects that cover the region, within cells.
+    parts: Vec<Rect>,
+}
+
+/// computes size of the image that covers the region (in global compositor coordinates) and
+/// rects of each of the sources within it.
+pub fn layout(region: Rect, sources: &[StitchSource], scale: StitchScale) -> StitchLayout {
+    match scale {
+        StitchScale::Max => {
+            let max_scale = sources.iter().map(StitchSource::scale).fold(1.0, f32::max);
+
+            let size = round(Vec2::new(region.width(), region.height()) * max_scale);
+            let dst_rects: Vec<Rect> = sources
+                .iter()
+                .map(|source| {
+                    let min = round((source.logical_rect.min - region.min) * max_scale);
+                    let size = round(
+                        Vec2::new(source.logical_rect.width(), source.logical_rect.height())
+                            * max_scale,
+                    );
+                    Rect::new(min, min + size)
+                })
+                .collect();
+            // NOTE: parts of sources outside of the region are outside of the image.
+            let image_rect = Rect::new(Vec2::ZERO, size);
+            let cells: Vec<Rect> = dst_rects
+                .iter()
+                .map(|dst_rect| dst_rect.constrain_to(&image_rect))
+                .collect();
+            StitchLayout {
+                size: Size::new(size.x as u32, size.y as u32),
+                dst_rects,
+                parts: cells.clone(),
+                cells,
+            }
+        }
+        StitchScale::Native => {
+            let spans_x: Vec<(f32, f32, f32)> = sources
+                .iter()
+                .map(|source| {
+                    let rect = source.logical_rect;
+                    (rect.min.x, rect.max.x, source.scale())
+                })
+                .collect();
+            let spans_y: Vec<(f32, f32, f32)> = sources
+                .iter()
+                .map(|source| {
+                    let rect = source.logical_rect;
+                    (rect.min.y, rect.max.y, source.scale_y())
+                })
+                .collect();
+            let mapping_x = native_axis(region.min.x, region.max.x, &spans_x);
+            let mapping_y = native_axis(region.min.y, region.max.y, &spans_y);
+
+            let mut dst_rects = Vec::with_capacity(sources.len());
+            let mut cells = Vec::with_capacity(sources.len());
+            let mut parts = Vec::with_capacity(sources.len());
+            for source in sources.iter() {
+                let rect = source.logical_rect.constrain_to(&region);
+                let cell = Rect::new(
+                    Vec2::new(
+                        native_pixel(&mapping_x, rect.min.x),
+                        native_pixel(&mapping_y, rect.min.y),
+                    ),
+                    Vec2::new(
+                        native_pixel(&mapping_x, rect.max.x),
+                        native_pixel(&mapping_y, rect.max.y),
+                    ),
+                );
+                // NOTE: parts of sources outside of the region are outside of the image.
+                let clipped = Vec2::new(
+                    ((rect.min.x - source.logical_rect.min.x) * source.scale()).round(),
+                    ((rect.min.y - source.logical_rect.min.y) * source.scale_y()).round(),
+                );
+                let min = cell.min - clipped;
+                dst_rects.push(Rect::new(min, min + source.size.as_vec2()));
+                let part_size = round(Vec2::new(
+                    rect.width() * source.scale(),
+                    rect.height() * source.scale_y(),
+                ));
+                parts.push(Rect::new(cell.min, cell.min + part_size).constrain_to(&cell));
+                cells.push(cell);
+            }
+            let size = Size::new(
+                mapping_x.last().unwrap().1 as u32,
+                mapping_y.last().unwrap().1 as u32,
+            );
+            StitchLayout {
+                size,
+                dst_rects,
+                cells,
+                parts,
+            }
+        }
+    }
+}
+
+fn is_empty(rect: &Rect) -> bool {
+    rect.min.x >= rect.max.x || rect.min.y >= rect.max.y
+}
+
+impl StitchLayout {
+    /// parts of dst rects that are drawn along with their normalized coordinates within dst rects.
+    /// nothing is drawn past parts, otherwise pixels of sources that are outside of the region
+    /// would end up in the image.
+    fn parts(&self) -> impl Iterator<Item = (Rect, Rect)> + '_ {
+        self.dst_rects
+            .iter()
+            .zip(self.parts.iter())
+            .map(|(dst_rect, part)| {
+                let part = *part;
+                let size = dst_rect.size();
+                let coords = Rect::new(
+                    (part.min - dst_rect.min) / size,
+                    (part.max - dst_rect.min) / size,
+                );
+                (part, coords)
+            })
+    }
+
+    /// parts of cells that dst rects do not cover.
+    fn gaps(&self) -> Vec<Rect> {
+        let mut gaps = Vec::new();
+        for ((part, _), cell) in self.parts().zip(self.cells.iter()) {
+            if is_empty(&part) {
+                gaps.push(*cell);
+                continue;
+            }
+            gaps.extend(
+                [
+                    Rect::new(cell.min, Vec2::new(cell.max.x, part.min.y)),
+                    Rect::new(Vec2::new(cell.min.x, part.max.y), cell.max),
+                    Rect::new(
+                        Vec2::new(cell.min.x, part.min.y),
+                        Vec2::new(part.min.x, part.max.y),
+                    ),
+                    Rect::new(
+                        Vec2::new(part.max.x, part.min.y),
+                        Vec2::new(cell.max.x, part.max.y),
+                    ),
+                ]
+                .into_iter()
+                .filter(|gap| !is_empty(gap)),
+            );
+        }
+        gaps
+    }
+}
+
+fn draw_gaps(draw_buffer: &mut DrawBuffer, layout: &StitchLayout) {
+    for gap in layout.gaps() {
+        draw_buffer.push_rect_filled(gap, RectFill::Color(GAP_BG));
+    }
+}
+
+pub fn draw(draw_buffer: &mut DrawBuffer, sources: &[StitchSource], layout: &StitchLayout) {
+    assert_eq!(sources.len(), layout.dst_rects.len());
+    for (source, (part, coords)) in sources.iter().zip(layout.parts()) {
+        if is_empty(&part) {
+            continue;
+        }
+        draw_buffer.push_rect_textured(
+            part,
+            source.texture_handle,
+            coords,
+            source.texture_transform,
+        );
+    }
+    draw_gaps(draw_buffer, layout);
+}
+
+/// redraws an image that was stitched with one layout (texture of it) with another layout of
+/// the same sources.
+pub fn draw_repacked(
+    draw_buffer: &mut DrawBuffer,
+    texture_handle: gl::sys::types::GLuint,
+    from: &StitchLayout,
+    to: &StitchLayout,
+) {
+    assert_eq!(from.dst_rects.len(), to.dst_rects.len());
+    // NOTE: parts of both layouts cover the same part of the region, thus coords stay within the
+    // texture.
+    let from_size = from.size.as_vec2();
+    for ((from_part, _), (to_part, _)) in from.parts().zip(to.parts()) {
+        if is_empty(&from_part) || is_empty(&to_part) {
+            continue;
+        }
+        draw_buffer.push_rect_textured(
+            to_part,
+            texture_handle,
+            Rect::new(from_part.min / from_size, from_part.max / from_size),
+            TextureTransform::default(),
+        );
+    }
+    draw_gaps(draw_buffer, to);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn source(x: f32, y: f32, width: f32, height: f32, scale: f32) -> StitchSource {
+        let min = Vec2::new(x, y);
+        StitchSource {
+            logical_rect: Rect::new(min, min + Vec2::new(width, height)),
+            size: Size::new((width * scale) as u32, (height * scale) as u32),
+            texture_handle: 0,
+            texture_transform: TextureTransform::default(),
+        }
+    }
+
+    #[test]
+    fn test_layout_mixed_scales() {
+        let sources = [
+            source(0.0, 0.0, 1920.0, 1080.0, 2.0),
+            source(1920.0, 0.0, 1920.0, 1080.0, 1.0),
+        ];
+        let region = Rect::new(Vec2::ZERO, Vec2::new(3840.0, 1080.0));
+
+        let layout_max = layout(region, &sources, StitchScale::Max);
+        assert_eq!(layout_max.size, Size::new(7680, 2160));
+        assert_eq!(
+            layout_max.dst_rects[0],
+            Rect::new(Vec2::ZERO, Vec2::new(3840.0, 2160.0))
+        );
+        assert_eq!(
+            layout_max.dst_rects[1],
+            Rect::new(Vec2::new(3840.0, 0.0), Vec2::new(7680.0, 2160.0))
+        );
+
+        let layout_native = layout(region, &sources, StitchScale::Native);
+        assert_eq!(layout_native.size, Size::new(5760, 2160));
+        assert_eq!(
+            layout_native.dst_rects[1],
+            Rect::new(Vec2::new(3840.0, 0.0), Vec2::new(5760.0, 1080.0))
+        );
+        // NOTE: rows below the less dense output are filled.
+        assert_eq!(
+            layout_native.cells[1],
+            Rect::new(Vec2::new(3840.0, 0.0), Vec2::new(5760.0, 2160.0))
+        );
+    }
+
+    #[test]
+    fn test_layout_native_leaves_no_columns_empty() {
+        let sources = [
+            source(-1920.0, 0.0, 1920.0, 1080.0, 1.0),
+            source(0.0, 0.0, 1920.0, 1080.0, 2.0),
+        ];
+        let region = Rect::new(Vec2::new(-1920.0, 0.0), Vec2::new(1920.0, 1080.0));
+
+        let layout = layout(region, &sources, StitchScale::Native);
+        assert_eq!(layout.size, Size::new(5760, 2160));
+        assert_eq!(
+            layout.dst_rects[0],
+            Rect::new(Vec2::ZERO, Vec2::new(1920.0, 1080.0))
+        );
+        assert_eq!(
+            layout.dst_rects[1],
+            Rect::new(Vec2::new(1920.0, 0.0), Vec2::new(5760.0, 2160.0))
+        );
+    }
+
+    #[test]
+    fn test_layout_partial_region() {
+        let sources = [source(-1280.0, 0.0, 1280.0, 1024.0, 1.0)];
+        let region = Rect::new(Vec2::new(-100.0, 10.0), Vec2::new(0.0, 110.0));
+
+        let layout_max = layout(region, &sources, StitchScale::Max);
+        assert_eq!(layout_max.size, Size::new(100, 100));
+        assert_eq!(layout_max.dst_rects[0].min, Vec2::new(-1180.0, -10.0));
+
+        let layout_native = layout(region, &sources, StitchScale::Native);
+        assert_eq!(layout_native.size, layout_max.size);
+        assert_eq!(layout_native.dst_rects, layout_max.dst_rects);
+    }
+
+    #[test]
+    fn test_layout_native_partial_region() {
+        let sources = [
+            source(-1920.0, 0.0, 1920.0, 1080.0, 1.0),
+            source(0.0, 0.0, 1920.0, 1080.0, 2.0),
+        ];
+        let region = Rect::new(Vec2::new(-100.0, 0.0), Vec2::new(100.0, 100.0));
+
+        let layout = layout(region, &sources, StitchScale::Native);
+        assert_eq!(layout.size, Size::new(300, 200));
+        assert_eq!(layout.dst_rects[0].min, Vec2::new(-1820.0, 0.0));
+        assert_eq!(layout.dst_rects[1].min, Vec2::new(100.0, 0.0));
+    }
+
+    #[test]
+    fn test_drawn_rects_stay_within_cells() {
+        let sources = [
+            source(-1920.0, 0.0, 1920.0, 1080.0, 1.0),
+            source(0.0, 0.0, 1920.0, 1080.0, 2.0),
+        ];
+        let region = Rect::new(Vec2::new(-100.0, 0.0), Vec2::new(100.0, 100.0));
+
+        for scale in [StitchScale::Max, StitchScale::Native] {
+            let layout = layout(region, &sources, scale);
+            let image_rect = Rect::new(Vec2::ZERO, layout.size.as_vec2());
+            let parts: Vec<(Rect, Rect)> = layout.parts().collect();
+            for ((part, coords), cell) in parts.iter().zip(layout.cells.iter()) {
+                assert_eq!(part.constrain_to(&image_rect), *part);
+                assert_eq!(part.constrain_to(cell), *part);
+                let unit = Rect::new(Vec2::ZERO, Vec2::splat(1.0));
+                assert_eq!(coords.constrain_to(&unit), *coords);
+            }
+            for gap in layout.gaps() {
+                assert!(layout.cells.iter().any(|cell| gap.constrain_to(cell) == gap));
+                assert!(!parts.iter().any(|(part, _)| part.intersects(&gap)));
+            }
+        }
+
+        let layout = layout(region, &sources, StitchScale::Native);
+        let (part, coords) = layout.parts().next().unwrap();
+        assert_eq!(part, Rect::new(Vec2::ZERO, Vec2::new(100.0, 100.0)));
+        assert_eq!(
+            coords,
+            Rect::new(
+                Vec2::new(1820.0 / 1920.0, 0.0),
+                Vec2::new(1.0, 100.0 / 1080.0)
+            )
+        );
+        // NOTE: rows below the less dense output are filled.
+        assert_eq!(layout.gaps(), vec![Rect::new(
+            Vec2::new(0.0, 100.0),
+            Vec2::new(100.0, 200.0)
+        )]);
+    }
+
+    #[test]
+    fn test_repacked_coords_stay_within_texture() {
+        let sources = [
+            source(-1920.0, 0.0, 1920.0, 1080.0, 1.0),
+            source(0.0, 0.0, 1920.0, 1080.0, 2.0),
+        ];
+        let region = Rect::new(Vec2::new(-100.0, 0.0), Vec2::new(100.0, 100.0));
+        let from = layout(region, &sources, StitchScale::Max);
+        let to = layout(region, &sources, StitchScale::Native);
+
+        let mut draw_buffer = DrawBuffer::default();
+        draw_repacked(&mut draw_buffer, 0, &from, &to);
+        for vertex in draw_buffer.vertices.iter() {
+            let tex_coord = vertex.tex_coord;
+            assert!((0.0..=1.0).contains(&tex_coord.x) && (0.0..=1.0).contains(&tex_coord.y));
+        }
+    }
+}
@@ -0,0 +1,170 @@
+use std::{
+    env,
+    ffi::{CString, c_char},
+    fs,
+    path::PathBuf,
+};
+
+use anyhow::{Context as _, anyhow};
+
+use crate::{config, geometry, gfx::Rect, metadata};
+
+/// has no extension, the one of the chosen image format is appended.
+pub const DEFAULT_FILENAME: &str = "%Y-%m-%d_%H%M%S_{output}";
+/// slurp-compatible.
+pub const DEFAULT_SELECT_FORMAT: &str = "{x},{y} {width}x{height}";
+
+pub struct TemplateData<'a> {
+    /// names of captured outputs.
+    pub outputs: &'a [String],
+    /// in global compositor coordinates.
+    pub geometry: Rect,
+}
+
+/// expands strftime fields using local time.
+fn strftime(template: &str, time: libc::time_t) -> anyhow::Result<String> {
+    if template.is_empty() {
+        return Ok(String::new());
+    }
+    let template = CString::new(template).context("template contains nul byte")?;
+
+    let mut tm: libc::tm = unsafe { std::mem::zeroed() };
+    if unsafe { libc::localtime_r(&time, &mut tm) }.is_null() {
+        return Err(anyhow!("could not get local time"));
+    }
+
+    let mut buf = vec![0u8; 4096];
+    let len = unsafe {
+        libc::strftime(
+            buf.as_mut_ptr() as *mut c_char,
+            buf.len(),
+            template.as_ptr(),
+            &tm,
+        )
+    };
+    // NOTE: zero is also returned when the result does not fit, it can't be told apart from an
+    // empty result.
+    if len == 0 {
+        return Err(anyhow!("template expands to nothing or is too long"));
+    }
+    buf.truncate(len);
+    String::from_utf8(buf).context("template expands to invalid utf-8")
+}
+
+fn expand_fields(template: &str, data: &TemplateData) -> String {
+    let x = data.geometry.min.x.round() as i32;
+    let y = data.geometry.min.y.round() as i32;
+    let width = data.geometry.width().round() as u32;
+    let height = data.geometry.height().round() as u32;
+    template
+        .replace("{output}", &data.outputs.join("+"))
+        .replace("{geometry}", &format!("{x},{y}_{width}x{height}"))
+        .replace("{x}", &x.to_string())
+        .replace("{y}", &y.to_string())
+        .replace("{width}", &width.to_string())
+        .replace("{height}", &height.to_string())
+}
+
+fn expand_home(path: &str) -> PathBuf {
+    match (path.strip_prefix("~/"), env::var_os("HOME")) {
+        (Some(rest), Some(home)) => PathBuf::from(home).join(rest),
+        _ => PathBuf::from(path),
+    }
+}
+
+/// reads XDG_PICTURES_DIR from the environment or from user-dirs.dirs (as written by
+/// xdg-user-dirs-update), falls back to ~/Pictures.
+pub fn pictures_dir() -> Option<PathBuf> {
+    if let Some(dir) = env::var_os("XDG_PICTURES_DIR").filter(|dir| !dir.is_empty()) {
+        return Some(PathBuf::from(dir));
+    }
+
+    let home = env::var_os("HOME").map(PathBuf::from)?;
+    let user_dirs = config::config_dir()
+        .and_then(|config_dir| fs::read_to_string(config_dir.join("user-dirs.dirs")).ok());
+    let pictures_dir = user_dirs.as_deref().and_then(|user_dirs| {
+        user_dirs.lines().find_map(|line| {
+            let value = line.trim().strip_prefix("XDG_PICTURES_DIR=")?;
+            let value = value.trim_matches('"');
+            Some(match value.strip_prefix("$HOME") {
+                Some(rest) => home.join(rest.trim_start_matches('/')),
+                None => PathBuf::from(value),
+            })
+        })
+    });
+    Some(pictures_dir.unwrap_or_else(|| home.join("Pictures")))
+}
+
+/// expands selection format. {geometry} is slurp-compatible here (unlike in filenames) and
+/// {scale} is of the output that covers the most of the selection.
+pub fn expand_selection(template: &str, data: &TemplateData, scale: f32) -> String {
+    let template = template
+        .replace("{geometry}", &geometry::format(&data.geometry))
+        .replace("{scale}", &metadata::format_scale(scale));
+    expand_fields(&template, data)
+}
+
+/// expands template into a path. relative paths are resolved against dir.
+pub fn expand(
+    template: &str,
+    dir: Option<&PathBuf>,
+    time: libc::time_t,
+    data: &TemplateData,
+) -> anyhow::Result<PathBuf> {
+    let path = expand_home(&expand_fields(&strftime(template, time)?, data));
+    match dir {
+        Some(dir) if path.is_relative() => Ok(dir.join(path)),
+        _ => Ok(path),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::gfx::Vec2;
+
+    #[test]
+    fn test_expand_fields() {
+        let data = TemplateData {
+            outputs: &["DP-1".to_string(), "HDMI-A-1".to_string()],
+            geometry: Rect::new(Vec2::new(-10.0, 20.0), Vec2::new(290.0, 220.0)),
+        };
+        assert_eq!(
+            expand_fields("{output}_{geometry}_{width}x{height}", &data),
+            "DP-1+HDMI-A-1_-10,20_300x200_300x200"
+        );
+    }
+
+    #[test]
+    fn test_expand_selection() {
+        let data = TemplateData {
+            outputs: &["DP-1".to_string()],
+            geometry: Rect::new(Vec2::new(-10.0, 20.0), Vec2::new(290.0, 220.0)),
+        };
+        assert_eq!(
+            expand_selection(DEFAULT_SELECT_FORMAT, &data, 1.0),
+            "-10,20 300x200"
+        );
+        assert_eq!(
+            expand_selection("{geometry} {output} {scale}", &data, 1.5),
+            "-10,20 300x200 DP-1 1.5"
+        );
+    }
+
+    #[test]
+    fn test_expand() {
+        let data = TemplateData {
+            outputs: &["eDP-1".to_string()],
+            geometry: Rect::new(Vec2::ZERO, Vec2::new(10.0, 10.0)),
+        };
+        let dir = PathBuf::from("/tmp");
+        assert_eq!(
+            expand("100%%_{output}.png", Some(&dir), 0, &data).unwrap(),
+            PathBuf::from("/tmp/100%_eDP-1.png")
+        );
+        assert_eq!(
+            expand("/abs/{x}.png", Some(&dir), 0, &data).unwrap(),
+            PathBuf::from("/abs/0.png")
+        );
+    }
+}
@@ -58,6 +58,11 @@ pub struct Lib {
     pub wl_display_connect: unsafe extern "C" fn(name: *const c_char) -> *mut wl_display,
     pub wl_display_disconnect: unsafe extern "C" fn(display: *mut wl_display) -> *mut c_void,
     pub wl_display_dispatch: unsafe extern "C" fn(display: *mut wl_display) -> c_int,
+    pub wl_display_dispatch_pending: unsafe extern "C" fn(display: *mut wl_display) -> c_int,
+    pub wl_display_prepare_read: unsafe extern "C" fn(display: *mut wl_display) -> c_int,
+    pub wl_display_read_events: unsafe extern "C" fn(display: *mut wl_display) -> c_int,
+    pub wl_display_cancel_read: unsafe extern "C" fn(display: *mut wl_display),
+    pub wl_display_get_fd: unsafe extern "C" fn(display: *mut wl_display) -> c_int,
     pub wl_display_roundtrip: unsafe extern "C" fn(display: *mut wl_display) -> c_int,
     pub wl_display_flush: unsafe extern "C" fn(display: *mut wl_display) -> c_int,
 
@@ -92,6 +97,11 @@ impl Lib {
             wl_display_connect: lib.lookup(b"wl_display_connect\0")?,
             wl_display_disconnect: lib.lookup(b"wl_display_disconnect\0")?,
             wl_display_dispatch: lib.lookup(b"wl_display_dispatch\0")?,
+            wl_display_dispatch_pending: lib.lookup(b"wl_display_dispatch_pending\0")?,
+            wl_display_prepare_read: lib.lookup(b"wl_display_prepare_read\0")?,
+            wl_display_read_events: lib.lookup(b"wl_display_read_events\0")?,
+            wl_display_cancel_read: lib.lookup(b"wl_display_cancel_read\0")?,
+            wl_display_get_fd: lib.lookup(b"wl_display_get_fd\0")?,
             wl_display_roundtrip: lib.lookup(b"wl_display_roundtrip\0")?,
             wl_display_flush: lib.lookup(b"wl_display_flush\0")?,
 
@@ -1,23 +1,262 @@
 use std::{
+    borrow::Cow,
     ffi::{CStr, CString, c_char, c_int, c_void},
+    os::unix::ffi::OsStrExt as _,
+    path::{Path, PathBuf},
     ptr::NonNull,
     rc::Rc,
 };
 
 use anyhow::{Context as _, anyhow};
 
-use crate::{Connection, wayland};
+use crate::{
+    Connection,
+    encoder::{self, EncodeOptions, ImageFormat},
+    wayland,
+};
+
+/// formats that are offered in addition to the preferred one, in order of preference.
+const IMAGE_FORMATS: &[ImageFormat] = &[ImageFormat::Png, ImageFormat::Jpeg, ImageFormat::Bmp];
+
+const MIME_TYPE_URI_LIST: &str = "text/uri-list";
+const MIME_TYPE_TEXT: &[&str] = &["text/plain;charset=utf-8", "text/plain", "UTF8_STRING"];
+
+pub struct ClipboardImage {
+    pub image: encoder::Image,
+    pub format: ImageFormat,
+    pub encode_options: EncodeOptions,
+    /// where the image was saved, if it was. offered as uri and plain text.
+    pub path: Option<PathBuf>,
+}
+
+enum ClipboardContent {
+    Image(ImageFormat),
+    UriList,
+    Text,
+}
+
+enum ClipboardDataOffer {
+    Image {
+        /// dropped once every format is encoded.
+        image: Option<encoder::Image>,
+        encode_options: EncodeOptions,
+        /// every offered format, the preferred one first.
+        formats: Vec<ImageFormat>,
+        /// images are encoded lazily on first request.
+        encoded: Vec<(ImageFormat, Vec<u8>)>,
+        /// where the image was saved, if it was.
+        path: Option<PathBuf>,
+    },
+    Text(String),
+}
 
-struct ClipboardDataOffer {
-    mime_type: CString,
-    data: Vec<u8>,
+impl ClipboardDataOffer {
+    fn image(image: ClipboardImage) -> Self {
+        let mut formats = vec![image.format];
+        for format in IMAGE_FORMATS {
+            if *format != image.format {
+                formats.push(*format);
+            }
+        }
+        Self::Image {
+            image: Some(image.image),
+            encode_options: image.encode_options,
+            formats,
+            encoded: Vec::new(),
+            path: image.path,
+        }
+    }
+
+    /// encodes every offered format and drops the pixels.
+    fn encode_all(&mut self) -> anyhow::Result<()> {
+        let formats = match self {
+            Self::Image { formats, .. } => formats.clone(),
+            Self::Text(_) => return Ok(()),
+        };
+        for format in formats {
+            self.encode(format)?;
+        }
+        if let Self::Image { image, .. } = self {
+            _ = image.take();
+        }
+        Ok(())
+    }
+
+    /// returns index of the encoded image, encodes it if it was not yet.
+    fn encode(&mut self, format: ImageFormat) -> anyhow::Result<usize> {
+        let Self::Image {
+            image,
+            encode_options,
+            encoded,
+            ..
+        } = self
+        else {
+            unreachable!();
+        };
+        if let Some(idx) = encoded.iter().position(|(f, _)| *f == format) {
+            return Ok(idx);
+        }
+        let image = image.as_ref().context("image is gone")?;
+        let data = encoder::encode(format, image, encode_options)
+            .with_context(|| format!("could not encode {}", format.mime_type()))?;
+        encoded.push((format, data));
+        Ok(encoded.len() - 1)
+    }
+
+    fn mime_types(&self) -> Vec<&'static str> {
+        match self {
+            Self::Image { formats, path, .. } => {
+                let mut mime_types: Vec<&'static str> =
+                    formats.iter().map(|format| format.mime_type()).collect();
+                if path.is_some() {
+                    mime_types.push(MIME_TYPE_URI_LIST);
+                    mime_types.extend_from_slice(MIME_TYPE_TEXT);
+                }
+                mime_types
+            }
+            Self::Text(_) => MIME_TYPE_TEXT.to_vec(),
+        }
+    }
+
+    fn content(&self, mime_type: &str) -> Option<ClipboardContent> {
+        let (formats, path) = match self {
+            Self::Image { formats, path, .. } => (formats, path),
+            Self::Text(_) => {
+                return MIME_TYPE_TEXT
+                    .contains(&mime_type)
+                    .then_some(ClipboardContent::Text);
+            }
+        };
+        if let Some(format) = formats
+            .iter()
+            .find(|format| format.mime_type() == mime_type)
+        {
+            return Some(ClipboardContent::Image(*format));
+        }
+        path.as_ref()?;
+        if mime_type == MIME_TYPE_URI_LIST {
+            Some(ClipboardContent::UriList)
+        } else if MIME_TYPE_TEXT.contains(&mime_type) {
+            Some(ClipboardContent::Text)
+        } else {
+            None
+        }
+    }
+
+    fn data(&mut self, content: ClipboardContent) -> anyhow::Result<Cow<'_, [u8]>> {
+        if let ClipboardContent::Image(format) = content {
+            let idx = self.encode(format)?;
+            let Self::Image { encoded, .. } = self else {
+                unreachable!();
+            };
+            return Ok(Cow::Borrowed(&encoded[idx].1));
+        }
+        let path = match self {
+            Self::Image { path, .. } => path,
+            Self::Text(text) => return Ok(Cow::Borrowed(text.as_bytes())),
+        };
+        let path = path.as_ref().unwrap();
+        match content {
+            ClipboardContent::UriList => {
+                Ok(Cow::Owned(format!("{}\r\n", file_uri(path)).into_bytes()))
+            }
+            ClipboardContent::Text => Ok(Cow::Borrowed(path.as_os_str().as_bytes())),
+            ClipboardContent::Image(_) => unreachable!(),
+        }
+    }
+}
+
+/// percent-encodes everything except unreserved characters and slashes.
+fn file_uri(path: &Path) -> String {
+    let mut uri = String::from("file://");
+    for b in path.as_os_str().as_bytes() {
+        match b {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'.' | b'_' | b'~' | b'/' => {
+                uri.push(*b as char)
+            }
+            _ => uri.push_str(&format!("%{b:02X}")),
+        }
+    }
+    uri
+}
+
+/// ext data control is the standardized version of wlr data control, they are identical.
+#[derive(Debug, Clone, Copy)]
+enum DataControlManager {
+    Ext(*mut wayland::ext_data_control_manager_v1),
+    Wlr(*mut wayland::zwlr_data_control_manager_v1),
+}
+
+#[derive(Debug, Clone, Copy)]
+enum DataControlDevice {
+    Ext(NonNull<wayland::ext_data_control_device_v1>),
+    Wlr(NonNull<wayland::zwlr_data_control_device_v1>),
+}
+
+impl DataControlDevice {
+    fn as_ptr(&self) -> *mut c_void {
+        match self {
+            Self::Ext(device) => device.as_ptr() as _,
+            Self::Wlr(device) => device.as_ptr() as _,
+        }
+    }
+
+    unsafe fn destroy(self, wayland_lib: &wayland::Lib) {
+        match self {
+            Self::Ext(device) => {
+                wayland::ext_data_control_device_v1_destroy(wayland_lib, device.as_ptr())
+            }
+            Self::Wlr(device) => {
+                wayland::zwlr_data_control_device_v1_destroy(wayland_lib, device.as_ptr())
+            }
+        }
+    }
+}
+
+/// a source that is set as one of the selections.
+#[derive(Debug, Clone, Copy)]
+enum DataSource {
+    DataDevice(NonNull<wayland::wl_data_source>),
+    PrimarySelection(NonNull<wayland::zwp_primary_selection_source_v1>),
+    ExtDataControl(NonNull<wayland::ext_data_control_source_v1>),
+    WlrDataControl(NonNull<wayland::zwlr_data_control_source_v1>),
+}
+
+impl DataSource {
+    fn as_ptr(&self) -> *mut c_void {
+        match self {
+            Self::DataDevice(source) => source.as_ptr() as _,
+            Self::PrimarySelection(source) => source.as_ptr() as _,
+            Self::ExtDataControl(source) => source.as_ptr() as _,
+            Self::WlrDataControl(source) => source.as_ptr() as _,
+        }
+    }
+
+    unsafe fn destroy(self, wayland_lib: &wayland::Lib) {
+        match self {
+            Self::DataDevice(source) => {
+                wayland::wl_data_source_destroy(wayland_lib, source.as_ptr())
+            }
+            Self::PrimarySelection(source) => {
+                wayland::zwp_primary_selection_source_v1_destroy(wayland_lib, source.as_ptr())
+            }
+            Self::ExtDataControl(source) => {
+                wayland::ext_data_control_source_v1_destroy(wayland_lib, source.as_ptr())
+            }
+            Self::WlrDataControl(source) => {
+                wayland::zwlr_data_control_source_v1_destroy(wayland_lib, source.as_ptr())
+            }
+        }
+    }
 }
 
 pub struct Clipboard {
     conn: Rc<Connection>,
 
     data_device: Option<NonNull<wayland::wl_data_device>>,
-    data_source: Option<NonNull<wayland::wl_data_source>>,
+    primary_selection_device: Option<NonNull<wayland::zwp_primary_selection_device_v1>>,
+    data_control_device: Option<DataControlDevice>,
+    sources: Vec<DataSource>,
     data_offer: Option<ClipboardDataOffer>,
 
     pub cancelled: bool,
@@ -40,6 +279,52 @@ unsafe fn write_all(fd: c_int, buf: *const c_void, count: libc::size_t) -> anyho
     Ok(())
 }
 
+/// serves the data offer, all sources share it.
+unsafe fn send(clipboard: &mut Clipboard, mime_type: *const c_char, fd: i32) {
+    let data_offer = clipboard
+        .data_offer
+        .as_mut()
+        .expect("data offer is missing huh?");
+
+    let mime_type = CStr::from_ptr(mime_type).to_string_lossy();
+    // NOTE: clients are not supposed to request mime types that were not offered, but nothing
+    // prevents them from doing so.
+    match data_offer.content(&mime_type) {
+        Some(content) => match data_offer.data(content) {
+            Ok(data) => {
+                if let Err(err) = write_all(fd, data.as_ptr() as _, data.len()) {
+                    log::error!("write_all failed: {err:?}");
+                }
+            }
+            Err(err) => log::error!("could not prepare {mime_type}: {err:?}"),
+        },
+        None => log::warn!("requested mime type {mime_type} was not offered"),
+    }
+    // do not do early return, fd must be closed.
+    libc::close(fd);
+}
+
+/// the source was replaced by another one.
+unsafe fn cancel(clipboard: &mut Clipboard, source: *mut c_void) {
+    if let Some(idx) = clipboard
+        .sources
+        .iter()
+        .position(|data_source| data_source.as_ptr() == source)
+    {
+        clipboard
+            .sources
+            .swap_remove(idx)
+            .destroy(clipboard.conn.libs.wayland);
+    }
+
+    // NOTE: once all selections are replaced there's nothing left to serve.
+    if clipboard.sources.is_empty() {
+        clipboard.release_devices();
+        _ = clipboard.data_offer.take();
+        clipboard.cancelled = true;
+    }
+}
+
 unsafe extern "C" fn handle_send(
     data: *mut c_void,
     _wl_data_source: *mut wayland::wl_data_source,
@@ -48,40 +333,16 @@ unsafe extern "C" fn handle_send(
 ) {
     log::debug!("wl_data_source.send");
 
-    let clipboard = &mut *(data as *mut Clipboard);
-    let data_offer = clipboard
-        .data_offer
-        .as_ref()
-        .expect("data offer is missing huh?");
-
-    // TODO: can we receive request for other mime, not the one that was
-    // offered? probably not?
-    let mime_type = CStr::from_ptr(mime_type);
-    assert!(data_offer.mime_type.as_ref().eq(mime_type));
-
-    if let Err(err) = write_all(fd, data_offer.data.as_ptr() as _, data_offer.data.len()) {
-        log::error!("write_all failed: {err:?}");
-        // do not do early return, fd must be closed.
-    }
-    libc::close(fd);
+    send(&mut *(data as *mut Clipboard), mime_type, fd);
 }
 
 unsafe extern "C" fn handle_cancelled(
     data: *mut c_void,
-    _wl_data_source: *mut wayland::wl_data_source,
+    wl_data_source: *mut wayland::wl_data_source,
 ) {
     log::debug!("wl_data_source.cancelled");
 
-    let clipboard = &mut *(data as *mut Clipboard);
-    if let Some(data_device) = clipboard.data_device.take() {
-        wayland::wl_data_device_release(clipboard.conn.libs.wayland, data_device.as_ptr());
-    }
-    if let Some(data_source) = clipboard.data_source.take() {
-        wayland::wl_data_source_destroy(clipboard.conn.libs.wayland, data_source.as_ptr());
-    }
-    _ = clipboard.data_offer.take();
-
-    clipboard.cancelled = true;
+    cancel(&mut *(data as *mut Clipboard), wl_data_source as _);
 }
 
 const WL_DATA_SOURCE_LISTENER: wayland::wl_data_source_listener =
@@ -94,27 +355,345 @@ const WL_DATA_SOURCE_LISTENER: wayland::wl_data_source_listener =
         action: wayland::noop_listener!(),
     };
 
+unsafe extern "C" fn handle_primary_selection_send(
+    data: *mut c_void,
+    _zwp_primary_selection_source_v1: *mut wayland::zwp_primary_selection_source_v1,
+    mime_type: *const c_char,
+    fd: i32,
+) {
+    log::debug!("zwp_primary_selection_source_v1.send");
+
+    send(&mut *(data as *mut Clipboard), mime_type, fd);
+}
+
+unsafe extern "C" fn handle_primary_selection_cancelled(
+    data: *mut c_void,
+    zwp_primary_selection_source_v1: *mut wayland::zwp_primary_selection_source_v1,
+) {
+    log::debug!("zwp_primary_selection_source_v1.cancelled");
+
+    cancel(
+        &mut *(data as *mut Clipboard),
+        zwp_primary_selection_source_v1 as _,
+    );
+}
+
+const ZWP_PRIMARY_SELECTION_SOURCE_V1_LISTENER: wayland::zwp_primary_selection_source_v1_listener =
+    wayland::zwp_primary_selection_source_v1_listener {
+        send: handle_primary_selection_send,
+        cancelled: handle_primary_selection_cancelled,
+    };
+
+unsafe extern "C" fn handle_ext_data_control_send(
+    data: *mut c_void,
+    _ext_data_control_source_v1: *mut wayland::ext_data_control_source_v1,
+    mime_type: *const c_char,
+    fd: i32,
+) {
+    log::debug!("ext_data_control_source_v1.send");
+
+    send(&mut *(data as *mut Clipboard), mime_type, fd);
+}
+
+unsafe extern "C" fn handle_ext_data_control_cancelled(
+    data: *mut c_void,
+    ext_data_control_source_v1: *mut wayland::ext_data_control_source_v1,
+) {
+    log::debug!("ext_data_control_source_v1.cancelled");
+
+    cancel(
+        &mut *(data as *mut Clipboard),
+        ext_data_control_source_v1 as _,
+    );
+}
+
+const EXT_DATA_CONTROL_SOURCE_V1_LISTENER: wayland::ext_data_control_source_v1_listener =
+    wayland::ext_data_control_source_v1_listener {
+        send: handle_ext_data_control_send,
+        cancelled: handle_ext_data_control_cancelled,
+    };
+
+unsafe extern "C" fn handle_wlr_data_control_send(
+    data: *mut c_void,
+    _zwlr_data_control_source_v1: *mut wayland::zwlr_data_control_source_v1,
+    mime_type: *const c_char,
+    fd: i32,
+) {
+    log::debug!("zwlr_data_control_source_v1.send");
+
+    send(&mut *(data as *mut Clipboard), mime_type, fd);
+}
+
+unsafe extern "C" fn handle_wlr_data_control_cancelled(
+    data: *mut c_void,
+    zwlr_data_control_source_v1: *mut wayland::zwlr_data_control_source_v1,
+) {
+    log::debug!("zwlr_data_control_source_v1.cancelled");
+
+    cancel(
+        &mut *(data as *mut Clipboard),
+        zwlr_data_control_source_v1 as _,
+    );
+}
+
+const ZWLR_DATA_CONTROL_SOURCE_V1_LISTENER: wayland::zwlr_data_control_source_v1_listener =
+    wayland::zwlr_data_control_source_v1_listener {
+        send: handle_wlr_data_control_send,
+        cancelled: handle_wlr_data_control_cancelled,
+    };
+
+/// the device is no longer valid, e.g. the seat is gone. a new one is created when needed.
+unsafe fn finish_data_control_device(clipboard: &mut Clipboard, device: *mut c_void) {
+    if let Some(data_control_device) = clipboard
+        .data_control_device
+        .take_if(|data_control_device| data_control_device.as_ptr() == device)
+    {
+        data_control_device.destroy(clipboard.conn.libs.wayland);
+    }
+}
+
+unsafe extern "C" fn handle_ext_data_control_data_offer(
+    data: *mut c_void,
+    _ext_data_control_device_v1: *mut wayland::ext_data_control_device_v1,
+    id: *mut wayland::ext_data_control_offer_v1,
+) {
+    log::debug!("ext_data_control_device_v1.data_offer");
+
+    // NOTE: selections of other clients are not read; offers are destroyed right away, later
+    // events that refer to them receive null.
+    let clipboard = &*(data as *mut Clipboard);
+    wayland::ext_data_control_offer_v1_destroy(clipboard.conn.libs.wayland, id);
+}
+
+unsafe extern "C" fn handle_ext_data_control_finished(
+    data: *mut c_void,
+    ext_data_control_device_v1: *mut wayland::ext_data_control_device_v1,
+) {
+    log::debug!("ext_data_control_device_v1.finished");
+
+    finish_data_control_device(
+        &mut *(data as *mut Clipboard),
+        ext_data_control_device_v1 as _,
+    );
+}
+
+unsafe extern "C" fn handle_ext_data_control_selection(
+    _data: *mut c_void,
+    _ext_data_control_device_v1: *mut wayland::ext_data_control_device_v1,
+    _id: *mut wayland::ext_data_control_offer_v1,
+) {
+    log::debug!("ext_data_control_device_v1.selection");
+}
+
+unsafe extern "C" fn handle_ext_data_control_primary_selection(
+    _data: *mut c_void,
+    _ext_data_control_device_v1: *mut wayland::ext_data_control_device_v1,
+    _id: *mut wayland::ext_data_control_offer_v1,
+) {
+    log::debug!("ext_data_control_device_v1.primary_selection");
+}
+
+const EXT_DATA_CONTROL_DEVICE_V1_LISTENER: wayland::ext_data_control_device_v1_listener =
+    wayland::ext_data_control_device_v1_listener {
+        data_offer: handle_ext_data_control_data_offer,
+        selection: handle_ext_data_control_selection,
+        finished: handle_ext_data_control_finished,
+        primary_selection: handle_ext_data_control_primary_selection,
+    };
+
+unsafe extern "C" fn handle_wlr_data_control_data_offer(
+    data: *mut c_void,
+    _zwlr_data_control_device_v1: *mut wayland::zwlr_data_control_device_v1,
+    id: *mut wayland::zwlr_data_control_offer_v1,
+) {
+    log::debug!("zwlr_data_control_device_v1.data_offer");
+
+    // NOTE: see the ext counterpart.
+    let clipboard = &*(data as *mut Clipboard);
+    wayland::zwlr_data_control_offer_v1_destroy(clipboard.conn.libs.wayland, id);
+}
+
+unsafe extern "C" fn handle_wlr_data_control_finished(
+    data: *mut c_void,
+    zwlr_data_control_device_v1: *mut wayland::zwlr_data_control_device_v1,
+) {
+    log::debug!("zwlr_data_control_device_v1.finished");
+
+    finish_data_control_device(
+        &mut *(data as *mut Clipboard),
+        zwlr_data_control_device_v1 as _,
+    );
+}
+
+unsafe extern "C" fn handle_wlr_data_control_selection(
+    _data: *mut c_void,
+    _zwlr_data_control_device_v1: *mut wayland::zwlr_data_control_device_v1,
+    _id: *mut wayland::zwlr_data_control_offer_v1,
+) {
+    log::debug!("zwlr_data_control_device_v1.selection");
+}
+
+unsafe extern "C" fn handle_wlr_data_control_primary_selection(
+    _data: *mut c_void,
+    _zwlr_data_control_device_v1: *mut wayland::zwlr_data_control_device_v1,
+    _id: *mut wayland::zwlr_data_control_offer_v1,
+) {
+    log::debug!("zwlr_data_control_device_v1.primary_selection");
+}
+
+const ZWLR_DATA_CONTROL_DEVICE_V1_LISTENER: wayland::zwlr_data_control_device_v1_listener =
+    wayland::zwlr_data_control_device_v1_listener {
+        data_offer: handle_wlr_data_control_data_offer,
+        selection: handle_wlr_data_control_selection,
+        finished: handle_wlr_data_control_finished,
+        primary_selection: handle_wlr_data_control_primary_selection,
+    };
+
+/// which selections to offer the image on.
+#[derive(Debug, Clone, Copy)]
+pub struct Selections {
+    pub clipboard: bool,
+    /// pasted with middle click.
+    pub primary: bool,
+}
+
 impl Clipboard {
     pub fn new_boxed(conn: &Rc<Connection>) -> Box<Self> {
         Box::new(Self {
             conn: Rc::clone(conn),
 
             data_device: None,
-            data_source: None,
+            primary_selection_device: None,
+            data_control_device: None,
+            sources: Vec::new(),
             data_offer: None,
 
             cancelled: false,
         })
     }
 
-    pub fn offer_data(
+    unsafe fn release_devices(&mut self) {
+        let wayland_lib = self.conn.libs.wayland;
+        if let Some(data_device) = self.data_device.take() {
+            wayland::wl_data_device_release(wayland_lib, data_device.as_ptr());
+        }
+        if let Some(device) = self.primary_selection_device.take() {
+            wayland::zwp_primary_selection_device_v1_destroy(wayland_lib, device.as_ptr());
+        }
+        if let Some(device) = self.data_control_device.take() {
+            device.destroy(wayland_lib);
+        }
+    }
+
+    /// prefers ext over wlr.
+    fn data_control_manager(&self) -> Option<DataControlManager> {
+        let globals = &self.conn.globals;
+        globals
+            .ext_data_control_manager
+            .map(DataControlManager::Ext)
+            .or(globals
+                .wlr_data_control_manager
+                .map(DataControlManager::Wlr))
+    }
+
+    pub fn offer_image(
+        &mut self,
+        serial: Option<u32>,
+        image: ClipboardImage,
+        selections: Selections,
+    ) -> anyhow::Result<()> {
+        self.offer(serial, ClipboardDataOffer::image(image), selections)
+    }
+
+    /// encodes every offered image format upfront, e.g. before serving the clipboard from a
+    /// detached process that should not keep the pixels around.
+    pub fn encode_all(&mut self) -> anyhow::Result<()> {
+        match self.data_offer.as_mut() {
+            Some(data_offer) => data_offer.encode_all(),
+            None => Ok(()),
+        }
+    }
+
+    pub fn offer_text(
+        &mut self,
+        serial: Option<u32>,
+        text: String,
+        selections: Selections,
+    ) -> anyhow::Result<()> {
+        self.offer(serial, ClipboardDataOffer::Text(text), selections)
+    }
+
+    /// regular data device and primary selection need a serial (thus keyboard focus), data
+    /// control is used when there's none or when the data device is not available.
+    fn offer(
         &mut self,
-        serial: u32,
-        mime_type: String,
-        data: Vec<u8>,
+        serial: Option<u32>,
+        data_offer: ClipboardDataOffer,
+        selections: Selections,
     ) -> anyhow::Result<()> {
-        let mime_type = CString::new(mime_type)?;
+        let mime_types = data_offer
+            .mime_types()
+            .into_iter()
+            .map(CString::new)
+            .collect::<Result<Vec<_>, _>>()?;
+
+        let data_control_manager = match serial {
+            Some(_) if self.conn.globals.data_device_manager.is_some() => None,
+            _ => self.data_control_manager(),
+        };
+        let primary_supported = match data_control_manager {
+            // NOTE: primary selection was added to wlr data control in version 2.
+            Some(DataControlManager::Wlr(manager)) => unsafe {
+                (self.conn.libs.wayland.wl_proxy_get_version)(manager as *mut wayland::wl_proxy)
+                    >= 2
+            },
+            Some(DataControlManager::Ext(_)) => true,
+            None => self.conn.globals.primary_selection_device_manager.is_some(),
+        };
+        let primary = match (selections.primary, primary_supported) {
+            (false, _) => false,
+            (true, true) => true,
+            (true, false) if selections.clipboard => {
+                log::warn!("primary selection is not available, only setting the clipboard");
+                false
+            }
+            (true, false) => return Err(anyhow!("primary selection is not available")),
+        };
+        if !selections.clipboard && !primary {
+            return Err(anyhow!("both clipboard and primary selection are disabled"));
+        }
 
+        match data_control_manager {
+            Some(manager) => {
+                log::info!("setting selection with data control");
+                if selections.clipboard {
+                    self.set_data_control_selection(manager, &mime_types, false)?;
+                }
+                if primary {
+                    self.set_data_control_selection(manager, &mime_types, true)?;
+                }
+            }
+            None => {
+                let serial = serial
+                    .context("no keyboard focus serial found and data control is not available")?;
+                if selections.clipboard {
+                    self.set_selection(serial, &mime_types)?;
+                }
+                if primary {
+                    self.set_primary_selection(serial, &mime_types)?;
+                }
+            }
+        }
+        unsafe {
+            (self.conn.libs.wayland.wl_display_flush)(self.conn.libs.wayland_display.as_ptr());
+        }
+
+        self.data_offer = Some(data_offer);
+
+        Ok(())
+    }
+
+    fn set_selection(&mut self, serial: u32, mime_types: &[CString]) -> anyhow::Result<()> {
         let data_device_manager = self
             .conn
             .globals
@@ -143,12 +722,16 @@ impl Clipboard {
         })
         .context("could not create data source")?;
 
+        for mime_type in mime_types.iter() {
+            unsafe {
+                wayland::wl_data_source_offer(
+                    self.conn.libs.wayland,
+                    data_source.as_ptr(),
+                    mime_type.as_ptr(),
+                );
+            }
+        }
         unsafe {
-            wayland::wl_data_source_offer(
-                self.conn.libs.wayland,
-                data_source.as_ptr(),
-                mime_type.as_ptr(),
-            );
             (self.conn.libs.wayland.wl_proxy_add_listener)(
                 data_source.as_ptr() as *mut wayland::wl_proxy,
                 &WL_DATA_SOURCE_LISTENER as *const wayland::wl_data_source_listener as _,
@@ -163,10 +746,207 @@ impl Clipboard {
                 data_source.as_ptr(),
                 serial,
             );
-            (self.conn.libs.wayland.wl_display_flush)(self.conn.libs.wayland_display.as_ptr());
         }
+        self.sources.push(DataSource::DataDevice(data_source));
 
-        self.data_offer = Some(ClipboardDataOffer { mime_type, data });
+        Ok(())
+    }
+
+    fn set_primary_selection(&mut self, serial: u32, mime_types: &[CString]) -> anyhow::Result<()> {
+        let device_manager = self
+            .conn
+            .globals
+            .primary_selection_device_manager
+            .context("primary selection device manager is not available")?;
+
+        if self.primary_selection_device.is_none() {
+            self.primary_selection_device = Some(
+                NonNull::new(unsafe {
+                    wayland::zwp_primary_selection_device_manager_v1_get_device(
+                        self.conn.libs.wayland,
+                        device_manager,
+                        self.conn.globals.seat.context("seat is not available")?,
+                    )
+                })
+                .context("could not get primary selection device")?,
+            );
+        }
+        let device = self.primary_selection_device.unwrap();
+
+        let source = NonNull::new(unsafe {
+            wayland::zwp_primary_selection_device_manager_v1_create_source(
+                self.conn.libs.wayland,
+                device_manager,
+            )
+        })
+        .context("could not create primary selection source")?;
+
+        for mime_type in mime_types.iter() {
+            unsafe {
+                wayland::zwp_primary_selection_source_v1_offer(
+                    self.conn.libs.wayland,
+                    source.as_ptr(),
+                    mime_type.as_ptr(),
+                );
+            }
+        }
+        unsafe {
+            (self.conn.libs.wayland.wl_proxy_add_listener)(
+                source.as_ptr() as *mut wayland::wl_proxy,
+                &ZWP_PRIMARY_SELECTION_SOURCE_V1_LISTENER
+                    as *const wayland::zwp_primary_selection_source_v1_listener
+                    as _,
+                self as *mut Self as *mut c_void,
+            );
+        }
+
+        unsafe {
+            wayland::zwp_primary_selection_device_v1_set_selection(
+                self.conn.libs.wayland,
+                device.as_ptr(),
+                source.as_ptr(),
+                serial,
+            );
+        }
+        self.sources.push(DataSource::PrimarySelection(source));
+
+        Ok(())
+    }
+
+    fn set_data_control_selection(
+        &mut self,
+        manager: DataControlManager,
+        mime_types: &[CString],
+        primary: bool,
+    ) -> anyhow::Result<()> {
+        let wayland_lib = self.conn.libs.wayland;
+        let seat = self.conn.globals.seat.context("seat is not available")?;
+        let data = self as *mut Self as *mut c_void;
+
+        let device = match self.data_control_device {
+            Some(device) => device,
+            None => {
+                let device = match manager {
+                    DataControlManager::Ext(manager) => unsafe {
+                        let device =
+                            NonNull::new(wayland::ext_data_control_manager_v1_get_data_device(
+                                wayland_lib,
+                                manager,
+                                seat,
+                            ))
+                            .context("could not get data control device")?;
+                        (wayland_lib.wl_proxy_add_listener)(
+                            device.as_ptr() as *mut wayland::wl_proxy,
+                            &EXT_DATA_CONTROL_DEVICE_V1_LISTENER
+                                as *const wayland::ext_data_control_device_v1_listener
+                                as _,
+                            data,
+                        );
+                        DataControlDevice::Ext(device)
+                    },
+                    DataControlManager::Wlr(manager) => unsafe {
+                        let device =
+                            NonNull::new(wayland::zwlr_data_control_manager_v1_get_data_device(
+                                wayland_lib,
+                                manager,
+                                seat,
+                            ))
+                            .context("could not get data control device")?;
+                        (wayland_lib.wl_proxy_add_listener)(
+                            device.as_ptr() as *mut wayland::wl_proxy,
+                            &ZWLR_DATA_CONTROL_DEVICE_V1_LISTENER
+                                as *const wayland::zwlr_data_control_device_v1_listener
+                                as _,
+                            data,
+                        );
+                        DataControlDevice::Wlr(device)
+                    },
+                };
+                self.data_control_device = Some(device);
+                device
+            }
+        };
+
+        let source = match manager {
+            DataControlManager::Ext(manager) => unsafe {
+                let source = NonNull::new(wayland::ext_data_control_manager_v1_create_data_source(
+                    wayland_lib,
+                    manager,
+                ))
+                .context("could not create data control source")?;
+                for mime_type in mime_types.iter() {
+                    wayland::ext_data_control_source_v1_offer(
+                        wayland_lib,
+                        source.as_ptr(),
+                        mime_type.as_ptr(),
+                    );
+                }
+                (wayland_lib.wl_proxy_add_listener)(
+                    source.as_ptr() as *mut wayland::wl_proxy,
+                    &EXT_DATA_CONTROL_SOURCE_V1_LISTENER
+                        as *const wayland::ext_data_control_source_v1_listener
+                        as _,
+                    data,
+                );
+                DataSource::ExtDataControl(source)
+            },
+            DataControlManager::Wlr(manager) => unsafe {
+                let source = NonNull::new(
+                    wayland::zwlr_data_control_manager_v1_create_data_source(wayland_lib, manager),
+                )
+                .context("could not create data control source")?;
+                for mime_type in mime_types.iter() {
+                    wayland::zwlr_data_control_source_v1_offer(
+                        wayland_lib,
+                        source.as_ptr(),
+                        mime_type.as_ptr(),
+                    );
+                }
+                (wayland_lib.wl_proxy_add_listener)(
+                    source.as_ptr() as *mut wayland::wl_proxy,
+                    &ZWLR_DATA_CONTROL_SOURCE_V1_LISTENER
+                        as *const wayland::zwlr_data_control_source_v1_listener
+                        as _,
+                    data,
+                );
+                DataSource::WlrDataControl(source)
+            },
+        };
+
+        unsafe {
+            match (device, source, primary) {
+                (DataControlDevice::Ext(device), DataSource::ExtDataControl(source), false) => {
+                    wayland::ext_data_control_device_v1_set_selection(
+                        wayland_lib,
+                        device.as_ptr(),
+                        source.as_ptr(),
+                    )
+                }
+                (DataControlDevice::Ext(device), DataSource::ExtDataControl(source), true) => {
+                    wayland::ext_data_control_device_v1_set_primary_selection(
+                        wayland_lib,
+                        device.as_ptr(),
+                        source.as_ptr(),
+                    )
+                }
+                (DataControlDevice::Wlr(device), DataSource::WlrDataControl(source), false) => {
+                    wayland::zwlr_data_control_device_v1_set_selection(
+                        wayland_lib,
+                        device.as_ptr(),
+                        source.as_ptr(),
+                    )
+                }
+                (DataControlDevice::Wlr(device), DataSource::WlrDataControl(source), true) => {
+                    wayland::zwlr_data_control_device_v1_set_primary_selection(
+                        wayland_lib,
+                        device.as_ptr(),
+                        source.as_ptr(),
+                    )
+                }
+                _ => unreachable!(),
+            }
+        }
+        self.sources.push(source);
 
         Ok(())
     }
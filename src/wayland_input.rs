@@ -3,6 +3,7 @@ use std::{
     ffi::{CString, c_char, c_void},
     ptr::NonNull,
     rc::Rc,
+    time::{Duration, Instant},
 };
 
 use anyhow::{Context, anyhow};
@@ -24,11 +25,19 @@ pub fn get_surface_id(surface: NonNull<wayland::wl_surface>) -> u64 {
 pub struct Input {
     conn: Rc<Connection>,
 
-    keyboard: NonNull<wayland::wl_keyboard>,
+    /// none once the seat is gone.
+    keyboard: Option<NonNull<wayland::wl_keyboard>>,
     xkb_context: Option<xkbcommon::Context>,
     pub keyboard_focused_surface_id: Option<u64>,
-
-    pointer: NonNull<wayland::wl_pointer>,
+    /// keys per second, zero disables repeat.
+    repeat_rate: i32,
+    /// milliseconds before a held key starts repeating.
+    repeat_delay: i32,
+    /// held key and when it repeats next.
+    repeat_key: Option<(u32, Instant)>,
+
+    /// none once the seat is gone.
+    pointer: Option<NonNull<wayland::wl_pointer>>,
     pointer_position: Vec2,
     pub pointer_focused_surface_id: Option<u64>,
     pointer_buttons: PointerButtons,
@@ -91,6 +100,7 @@ unsafe extern "C" fn handle_keyboard_enter(
         kind: KeyboardEventKind::Enter,
         surface_id,
         mods: input.xkb_context.as_ref().unwrap().mods.clone(),
+        text: None,
     };
     input.events.push_back(Event::Keyboard(keyboard_event));
 }
@@ -113,11 +123,13 @@ unsafe extern "C" fn handle_keyboard_leave(
 
     input.keyboard_focused_surface_id = None;
     input.serial_tracker.reset_serial(SerialType::KeyboardEnter);
+    input.repeat_key = None;
 
     let keyboard_event = KeyboardEvent {
         kind: KeyboardEventKind::Leave,
         surface_id,
         mods: input.xkb_context.as_ref().unwrap().mods.clone(),
+        text: None,
     };
     input.events.push_back(Event::Keyboard(keyboard_event));
 }
@@ -136,15 +148,33 @@ unsafe extern "C" fn handle_keyboard_key(
     assert!(input.xkb_context.is_some());
     assert!(input.keyboard_focused_surface_id.is_some());
 
+    let xkb_context = input.xkb_context.as_ref().unwrap();
     let scancode = Scancode::from_int(key);
+    let (kind, text) = match state {
+        wayland::WL_KEYBOARD_KEY_STATE_PRESSED => {
+            // NOTE: only the last pressed key repeats.
+            input.repeat_key = (input.repeat_rate > 0 && xkb_context.key_repeats(key)).then(|| {
+                let delay = Duration::from_millis(input.repeat_delay.max(0) as u64);
+                (key, Instant::now() + delay)
+            });
+            (
+                KeyboardEventKind::Press { scancode },
+                xkb_context.key_get_utf8(key),
+            )
+        }
+        wayland::WL_KEYBOARD_KEY_STATE_RELEASED => {
+            if input.repeat_key.is_some_and(|(repeat_key, _)| repeat_key == key) {
+                input.repeat_key = None;
+            }
+            (KeyboardEventKind::Release { scancode }, None)
+        }
+        _ => unreachable!("unsupported key state {state}"),
+    };
     let keyboard_event = KeyboardEvent {
-        kind: match state {
-            wayland::WL_KEYBOARD_KEY_STATE_PRESSED => KeyboardEventKind::Press { scancode },
-            wayland::WL_KEYBOARD_KEY_STATE_RELEASED => KeyboardEventKind::Release { scancode },
-            _ => unreachable!("unsupported key state {state}"),
-        },
+        kind,
         surface_id: input.keyboard_focused_surface_id.unwrap(),
-        mods: input.xkb_context.as_ref().unwrap().mods.clone(),
+        mods: xkb_context.mods.clone(),
+        text,
     };
     input.events.push_back(Event::Keyboard(keyboard_event));
 }
@@ -173,13 +203,29 @@ unsafe extern "C" fn handle_keyboard_modifiers(
     );
 }
 
+unsafe extern "C" fn handle_keyboard_repeat_info(
+    data: *mut c_void,
+    _wl_keyboard: *mut wayland::wl_keyboard,
+    rate: i32,
+    delay: i32,
+) {
+    log::debug!("wl_keyboard.repeat_info (rate {rate}, delay {delay})");
+
+    let input = &mut *(data as *mut Input);
+    input.repeat_rate = rate;
+    input.repeat_delay = delay;
+    if rate <= 0 {
+        input.repeat_key = None;
+    }
+}
+
 const WL_KEYBOARD_LISTENER: wayland::wl_keyboard_listener = wayland::wl_keyboard_listener {
     keymap: handle_keyboard_keymap,
     enter: handle_keyboard_enter,
     leave: handle_keyboard_leave,
     key: handle_keyboard_key,
     modifiers: handle_keyboard_modifiers,
-    repeat_info: wayland::noop_listener!(),
+    repeat_info: handle_keyboard_repeat_info,
 };
 
 unsafe extern "C" fn handle_pointer_enter(
@@ -389,11 +435,16 @@ impl Input {
         uninit.write(Self {
             conn: Rc::clone(conn),
 
-            keyboard,
+            keyboard: Some(keyboard),
             xkb_context: None,
             keyboard_focused_surface_id: None,
+            // NOTE: keyboards older than version 4 do not send repeat info, these are common
+            // compositor defaults.
+            repeat_rate: 25,
+            repeat_delay: 600,
+            repeat_key: None,
 
-            pointer,
+            pointer: Some(pointer),
             pointer_position: Vec2::ZERO,
             pointer_focused_surface_id: None,
             pointer_buttons: PointerButtons::default(),
@@ -408,7 +459,79 @@ impl Input {
         Ok(unsafe { uninit.assume_init() })
     }
 
+    /// releases keyboard and pointer of the seat that was removed, they must not be used after
+    /// that.
+    pub fn release_devices(&mut self) {
+        let wayland = self.conn.libs.wayland;
+        // NOTE: release requests were added in version 3, older objects can only be destroyed
+        // locally.
+        let version =
+            |proxy: *mut wayland::wl_proxy| unsafe { (wayland.wl_proxy_get_version)(proxy) };
+        if let Some(keyboard) = self.keyboard.take() {
+            let proxy = keyboard.as_ptr() as *mut wayland::wl_proxy;
+            if version(proxy) >= 3 {
+                unsafe { wayland::wl_keyboard_release(wayland, keyboard.as_ptr()) };
+            } else {
+                unsafe { (wayland.wl_proxy_destroy)(proxy) };
+            }
+            log::info!("released keyboard");
+        }
+        if let Some(pointer) = self.pointer.take() {
+            let proxy = pointer.as_ptr() as *mut wayland::wl_proxy;
+            if version(proxy) >= 3 {
+                unsafe { wayland::wl_pointer_release(wayland, pointer.as_ptr()) };
+            } else {
+                unsafe { (wayland.wl_proxy_destroy)(proxy) };
+            }
+            log::info!("released pointer");
+        }
+        self.keyboard_focused_surface_id = None;
+        self.repeat_key = None;
+        self.pointer_focused_surface_id = None;
+        self.pointer_frame_events.clear();
+    }
+
+    /// time left until the held key repeats, if any key is held.
+    pub fn key_repeat_timeout(&self) -> Option<Duration> {
+        let (_, repeat_at) = self.repeat_key?;
+        Some(repeat_at.saturating_duration_since(Instant::now()))
+    }
+
+    /// pushes a repeat event of the held key if it is due.
+    pub fn repeat_key(&mut self) {
+        let Some((key, repeat_at)) = self.repeat_key else {
+            return;
+        };
+        let now = Instant::now();
+        if now < repeat_at {
+            return;
+        }
+        let (Some(xkb_context), Some(surface_id)) = (
+            self.xkb_context.as_ref(),
+            self.keyboard_focused_surface_id,
+        ) else {
+            self.repeat_key = None;
+            return;
+        };
+
+        let keyboard_event = KeyboardEvent {
+            kind: KeyboardEventKind::Repeat {
+                scancode: Scancode::from_int(key),
+            },
+            surface_id,
+            mods: xkb_context.mods.clone(),
+            text: unsafe { xkb_context.key_get_utf8(key) },
+        };
+        self.events.push_back(Event::Keyboard(keyboard_event));
+        // NOTE: next repeat is scheduled from now, repeats that were missed are not caught up.
+        let interval = Duration::from_secs(1) / self.repeat_rate.max(1) as u32;
+        self.repeat_key = Some((key, now + interval));
+    }
+
     pub fn set_cursor_shape(&self, cursor_shape: CursorShape) -> anyhow::Result<()> {
+        let Some(pointer) = self.pointer else {
+            return Ok(());
+        };
         let Some(serial) = self.serial_tracker.get_serial(SerialType::PointerEnter) else {
             log::warn!("no pointer enter serial found");
             return Ok(());
@@ -465,7 +588,7 @@ impl Input {
 
             wayland::wl_pointer_set_cursor(
                 self.conn.libs.wayland,
-                self.pointer.as_ptr(),
+                pointer.as_ptr(),
                 serial,
                 self.cursor_surface.as_ptr(),
                 cursor_image.hotspot_x as i32,
@@ -0,0 +1,438 @@
+use std::{
+    ffi::{CStr, c_char, c_void},
+    ptr::NonNull,
+};
+
+use anyhow::Context as _;
+
+use crate::{
+    gfx::{Rect, Size, TextureTransform, Vec2},
+    wayland,
+};
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct OutputMode {
+    pub width: i32,
+    pub height: i32,
+    /// in mHz.
+    pub refresh: i32,
+    pub current: bool,
+    pub preferred: bool,
+}
+
+pub struct Output {
+    wayland_lib: &'static wayland::Lib,
+    /// name of the registry global, used to handle removal.
+    pub global_name: u32,
+    pub wl_output: NonNull<wayland::wl_output>,
+    xdg_output: Option<NonNull<wayland::zxdg_output_v1>>,
+    /// whether initial events were received.
+    pub done: bool,
+    /// whether geometry event was received, see [`Self::update_done_v1`].
+    has_geometry: bool,
+
+    pub name: Option<String>,
+    pub description: Option<String>,
+    pub make: Option<String>,
+    pub model: Option<String>,
+    /// position in the global compositor space as reported by wl_output; prefer logical position.
+    pub position: Vec2,
+    /// in millimeters.
+    pub physical_size: Size,
+    pub modes: Vec<OutputMode>,
+    pub scale: i32,
+    pub transform: u32,
+    /// position and size in the global compositor space, comes from xdg output.
+    pub logical_position: Option<Vec2>,
+    pub logical_size: Option<Vec2>,
+}
+
+impl Drop for Output {
+    fn drop(&mut self) {
+        if let Some(xdg_output) = self.xdg_output.take() {
+            unsafe { wayland::zxdg_output_v1_destroy(self.wayland_lib, xdg_output.as_ptr()) };
+        }
+
+        unsafe {
+            let wl_proxy = self.wl_output.as_ptr() as *mut wayland::wl_proxy;
+            // NOTE: release request was added in version 3.
+            if (self.wayland_lib.wl_proxy_get_version)(wl_proxy) >= 3 {
+                wayland::wl_output_release(self.wayland_lib, self.wl_output.as_ptr());
+            } else {
+                (self.wayland_lib.wl_proxy_destroy)(wl_proxy);
+            }
+        }
+    }
+}
+
+unsafe fn string_from_ptr(ptr: *const c_char) -> Option<String> {
+    if ptr.is_null() {
+        return None;
+    }
+    Some(CStr::from_ptr(ptr).to_string_lossy().into_owned())
+}
+
+unsafe extern "C" fn handle_geometry(
+    data: *mut c_void,
+    _wl_output: *mut wayland::wl_output,
+    x: i32,
+    y: i32,
+    physical_width: i32,
+    physical_height: i32,
+    _subpixel: i32,
+    make: *const c_char,
+    model: *const c_char,
+    transform: i32,
+) {
+    log::debug!("wl_output.geometry");
+
+    let output = &mut *(data as *mut Output);
+    output.position = Vec2::new(x as f32, y as f32);
+    output.physical_size = Size::new(physical_width.max(0) as u32, physical_height.max(0) as u32);
+    output.make = string_from_ptr(make);
+    output.model = string_from_ptr(model);
+    output.transform = transform as u32;
+    output.has_geometry = true;
+    output.update_done_v1();
+}
+
+unsafe extern "C" fn handle_mode(
+    data: *mut c_void,
+    _wl_output: *mut wayland::wl_output,
+    flags: u32,
+    width: i32,
+    height: i32,
+    refresh: i32,
+) {
+    log::debug!("wl_output.mode");
+
+    let output = &mut *(data as *mut Output);
+    let mode = OutputMode {
+        width,
+        height,
+        refresh,
+        current: flags & wayland::WL_OUTPUT_MODE_CURRENT != 0,
+        preferred: flags & wayland::WL_OUTPUT_MODE_PREFERRED != 0,
+    };
+    if mode.current {
+        for mode in output.modes.iter_mut() {
+            mode.current = false;
+        }
+    }
+    match output
+        .modes
+        .iter_mut()
+        .find(|m| m.width == width && m.height == height && m.refresh == refresh)
+    {
+        Some(existing) => *existing = mode,
+        None => output.modes.push(mode),
+    }
+    output.update_done_v1();
+}
+
+unsafe extern "C" fn handle_done(data: *mut c_void, _wl_output: *mut wayland::wl_output) {
+    log::debug!("wl_output.done");
+
+    let output = &mut *(data as *mut Output);
+    output.done = true;
+}
+
+unsafe extern "C" fn handle_scale(
+    data: *mut c_void,
+    _wl_output: *mut wayland::wl_output,
+    factor: i32,
+) {
+    log::debug!("wl_output.scale");
+
+    let output = &mut *(data as *mut Output);
+    output.scale = factor;
+}
+
+unsafe extern "C" fn handle_name(
+    data: *mut c_void,
+    _wl_output: *mut wayland::wl_output,
+    name: *const c_char,
+) {
+    log::debug!("wl_output.name");
+
+    let output = &mut *(data as *mut Output);
+    output.name = string_from_ptr(name);
+}
+
+unsafe extern "C" fn handle_description(
+    data: *mut c_void,
+    _wl_output: *mut wayland::wl_output,
+    description: *const c_char,
+) {
+    log::debug!("wl_output.description");
+
+    let output = &mut *(data as *mut Output);
+    output.description = string_from_ptr(description);
+}
+
+const WL_OUTPUT_LISTENER: wayland::wl_output_listener = wayland::wl_output_listener {
+    geometry: handle_geometry,
+    mode: handle_mode,
+    done: handle_done,
+    scale: handle_scale,
+    name: handle_name,
+    description: handle_description,
+};
+
+unsafe extern "C" fn handle_xdg_logical_position(
+    data: *mut c_void,
+    _zxdg_output_v1: *mut wayland::zxdg_output_v1,
+    x: i32,
+    y: i32,
+) {
+    log::debug!("zxdg_output_v1.logical_position");
+
+    let output = &mut *(data as *mut Output);
+    output.logical_position = Some(Vec2::new(x as f32, y as f32));
+}
+
+unsafe extern "C" fn handle_xdg_logical_size(
+    data: *mut c_void,
+    _zxdg_output_v1: *mut wayland::zxdg_output_v1,
+    width: i32,
+    height: i32,
+) {
+    log::debug!("zxdg_output_v1.logical_size");
+
+    let output = &mut *(data as *mut Output);
+    output.logical_size = Some(Vec2::new(width as f32, height as f32));
+}
+
+unsafe extern "C" fn handle_xdg_name(
+    data: *mut c_void,
+    _zxdg_output_v1: *mut wayland::zxdg_output_v1,
+    name: *const c_char,
+) {
+    log::debug!("zxdg_output_v1.name");
+
+    // NOTE: wl_output (v4) name takes precedence, xdg output name is for older compositors.
+    let output = &mut *(data as *mut Output);
+    if output.name.is_none() {
+        output.name = string_from_ptr(name);
+    }
+}
+
+unsafe extern "C" fn handle_xdg_description(
+    data: *mut c_void,
+    _zxdg_output_v1: *mut wayland::zxdg_output_v1,
+    description: *const c_char,
+) {
+    log::debug!("zxdg_output_v1.description");
+
+    let output = &mut *(data as *mut Output);
+    if output.description.is_none() {
+        output.description = string_from_ptr(description);
+    }
+}
+
+const ZXDG_OUTPUT_V1_LISTENER: wayland::zxdg_output_v1_listener =
+    wayland::zxdg_output_v1_listener {
+        logical_position: handle_xdg_logical_position,
+        logical_size: handle_xdg_logical_size,
+        done: wayland::noop_listener!(),
+        name: handle_xdg_name,
+        description: handle_xdg_description,
+    };
+
+impl Output {
+    pub fn new_boxed(
+        wayland_lib: &'static wayland::Lib,
+        global_name: u32,
+        wl_output: NonNull<wayland::wl_output>,
+    ) -> Box<Self> {
+        let mut output = Box::new(Self {
+            wayland_lib,
+            global_name,
+            wl_output,
+            xdg_output: None,
+            done: false,
+            has_geometry: false,
+
+            name: None,
+            description: None,
+            make: None,
+            model: None,
+            position: Vec2::ZERO,
+            physical_size: Size::default(),
+            modes: Vec::new(),
+            scale: 1,
+            transform: wayland::WL_OUTPUT_TRANSFORM_NORMAL,
+            logical_position: None,
+            logical_size: None,
+        });
+
+        unsafe {
+            (wayland_lib.wl_proxy_add_listener)(
+                wl_output.as_ptr() as *mut wayland::wl_proxy,
+                &WL_OUTPUT_LISTENER as *const wayland::wl_output_listener as _,
+                output.as_mut() as *mut Self as *mut c_void,
+            );
+        }
+
+        output
+    }
+
+    /// xdg output manager may be bound before or after the output, thus this is a separate step.
+    pub fn bind_xdg_output(
+        &mut self,
+        wayland_lib: &'static wayland::Lib,
+        xdg_output_manager: *mut wayland::zxdg_output_manager_v1,
+    ) -> anyhow::Result<()> {
+        assert!(self.xdg_output.is_none());
+
+        let xdg_output = NonNull::new(unsafe {
+            wayland::zxdg_output_manager_v1_get_xdg_output(
+                wayland_lib,
+                xdg_output_manager,
+                self.wl_output.as_ptr(),
+            )
+        })
+        .context("could not get xdg output")?;
+        unsafe {
+            (wayland_lib.wl_proxy_add_listener)(
+                xdg_output.as_ptr() as *mut wayland::wl_proxy,
+                &ZXDG_OUTPUT_V1_LISTENER as *const wayland::zxdg_output_v1_listener as _,
+                self as *mut Self as *mut c_void,
+            );
+        }
+        self.xdg_output = Some(xdg_output);
+
+        Ok(())
+    }
+
+    /// done event was added in version 2, older outputs are considered done once their geometry
+    /// and current mode are received.
+    unsafe fn update_done_v1(&mut self) {
+        let wl_proxy = self.wl_output.as_ptr() as *mut wayland::wl_proxy;
+        if (self.wayland_lib.wl_proxy_get_version)(wl_proxy) < 2
+            && self.has_geometry
+            && self.current_mode().is_some()
+        {
+            self.done = true;
+        }
+    }
+
+    pub fn current_mode(&self) -> Option<&OutputMode> {
+        self.modes.iter().find(|mode| mode.current)
+    }
+
+    /// rect in the global compositor space.
+    pub fn logical_rect(&self) -> Option<Rect> {
+        if let (Some(position), Some(size)) = (self.logical_position, self.logical_size) {
+            return Some(Rect::new(position, position + size));
+        }
+
+        // NOTE: without xdg output logical geometry is derived from the current mode, integer
+        // scale and transform; this is wrong with fractional scales.
+        let mode = self.current_mode()?;
+        let size = self
+            .texture_transform(false)
+            .apply_size(Size::new(mode.width as u32, mode.height as u32))
+            .as_vec2()
+            * (1.0 / self.scale.max(1) as f32);
+        Some(Rect::new(self.position, self.position + size))
+    }
+
+    /// physical pixels per logical pixel, may be fractional.
+    pub fn pixel_scale(&self) -> Option<f32> {
+        let mode = self.current_mode()?;
+        let pixels = self
+            .texture_transform(false)
+            .apply_size(Size::new(mode.width as u32, mode.height as u32));
+        Some(pixels.width as f32 / self.logical_rect()?.width())
+    }
+
+    /// pixel density of the current mode as it appears on screen (transform is accounted for).
+    /// outputs without physical size (e.g. projectors or virtual ones) report zeroes.
+    pub fn pixels_per_meter(&self) -> Option<Vec2> {
+        if self.physical_size.width == 0 || self.physical_size.height == 0 {
+            return None;
+        }
+        let mode = self.current_mode()?;
+        let transform = self.texture_transform(false);
+        let pixels = transform.apply_size(Size::new(mode.width as u32, mode.height as u32));
+        let millimeters = transform.apply_size(self.physical_size);
+        let pixels_per_millimeter = Vec2::new(
+            pixels.width as f32 / millimeters.width as f32,
+            pixels.height as f32 / millimeters.height as f32,
+        );
+        Some(pixels_per_millimeter * 1000.0)
+    }
+
+    /// screencopy buffers are not transformed, they match output's hardware orientation (and may
+    /// additionally be y-inverted, see screencopy frame flags). this returns a transform that
+    /// maps what's on screen into the buffer.
+    pub fn texture_transform(&self, y_invert: bool) -> TextureTransform {
+        texture_transform(self.transform, y_invert)
+    }
+}
+
+fn texture_transform(transform: u32, y_invert: bool) -> TextureTransform {
+    use wayland::*;
+    let quarter_turns = match transform {
+        WL_OUTPUT_TRANSFORM_NORMAL | WL_OUTPUT_TRANSFORM_FLIPPED => 0,
+        WL_OUTPUT_TRANSFORM_90 | WL_OUTPUT_TRANSFORM_FLIPPED_90 => 1,
+        WL_OUTPUT_TRANSFORM_180 | WL_OUTPUT_TRANSFORM_FLIPPED_180 => 2,
+        WL_OUTPUT_TRANSFORM_270 | WL_OUTPUT_TRANSFORM_FLIPPED_270 => 3,
+        transform => {
+            log::warn!("unknown output transform {transform}");
+            0
+        }
+    };
+    TextureTransform {
+        quarter_turns,
+        flip_x: transform >= WL_OUTPUT_TRANSFORM_FLIPPED,
+        flip_y: y_invert,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const TOP_LEFT: Vec2 = Vec2::new(0.0, 0.0);
+    const TOP_RIGHT: Vec2 = Vec2::new(1.0, 0.0);
+    const BOTTOM_RIGHT: Vec2 = Vec2::new(1.0, 1.0);
+    const BOTTOM_LEFT: Vec2 = Vec2::new(0.0, 1.0);
+
+    /// maps top left, top right, bottom right and bottom left corners of the screen.
+    fn map_corners(transform: u32) -> [Vec2; 4] {
+        let texture_transform = texture_transform(transform, false);
+        [TOP_LEFT, TOP_RIGHT, BOTTOM_RIGHT, BOTTOM_LEFT].map(|coord| texture_transform.apply(coord))
+    }
+
+    #[test]
+    fn test_texture_transform() {
+        assert_eq!(map_corners(wayland::WL_OUTPUT_TRANSFORM_NORMAL), [
+            TOP_LEFT,
+            TOP_RIGHT,
+            BOTTOM_RIGHT,
+            BOTTOM_LEFT
+        ]);
+        // NOTE: the buffer is rotated counter-clockwise relative to the screen.
+        assert_eq!(map_corners(wayland::WL_OUTPUT_TRANSFORM_90), [
+            BOTTOM_LEFT,
+            TOP_LEFT,
+            TOP_RIGHT,
+            BOTTOM_RIGHT
+        ]);
+        assert_eq!(map_corners(wayland::WL_OUTPUT_TRANSFORM_270), [
+            TOP_RIGHT,
+            BOTTOM_RIGHT,
+            BOTTOM_LEFT,
+            TOP_LEFT
+        ]);
+        // NOTE: flipped first, rotated second.
+        assert_eq!(map_corners(wayland::WL_OUTPUT_TRANSFORM_FLIPPED_90), [
+            TOP_LEFT,
+            BOTTOM_LEFT,
+            BOTTOM_RIGHT,
+            TOP_RIGHT
+        ]);
+    }
+}
@@ -22,6 +22,8 @@ pub struct Overlay {
     pub logical_size: Option<Size>,
 
     pub acked_first_configure: bool,
+    /// compositor will not show the surface anymore, e.g. because the output is gone.
+    pub closed: bool,
     window: Option<*mut wayland_egl::wl_egl_window>,
     pub window_surface: Option<egl::WindowSurface>,
 }
@@ -95,9 +97,20 @@ unsafe extern "C" fn handle_configure(
     );
     overlay.acked_first_configure = true;
 
-    // TODO: this is not the actual logical size of the screen. must get it from xdg shell or
-    // something instead.
+    // NOTE: surface is anchored to all edges and has exclusive zone of -1, thus configured size
+    // matches logical size of the output. global position comes from the output.
     let logical_size = Size::new(width, height);
+    let output_logical_size = overlay
+        .conn
+        .globals
+        .get_output(overlay.output)
+        .and_then(|output| output.logical_rect())
+        .map(|rect| Size::new(rect.width() as u32, rect.height() as u32));
+    if output_logical_size.is_some_and(|output_logical_size| output_logical_size != logical_size) {
+        log::warn!(
+            "configured size {logical_size:?} does not match output logical size {output_logical_size:?}"
+        );
+    }
 
     overlay
         .configure(None, Some(logical_size))
@@ -105,11 +118,13 @@ unsafe extern "C" fn handle_configure(
 }
 
 unsafe extern "C" fn handle_closed(
-    _data: *mut c_void,
+    data: *mut c_void,
     _zwlr_layer_surface_v1: *mut wayland::zwlr_layer_surface_v1,
 ) {
     log::debug!("zwlr_layer_surface_v1.closed");
-    unimplemented!();
+
+    let overlay = &mut *(data as *mut Overlay);
+    overlay.closed = true;
 }
 
 const ZWLR_LAYER_SURFACE_V1_LISTENER: wayland::zwlr_layer_surface_v1_listener =
@@ -231,6 +246,7 @@ impl Overlay {
             logical_size: None,
 
             acked_first_configure: false,
+            closed: false,
             window: None,
             window_surface: None,
         });
@@ -1,14 +1,55 @@
 use std::{
     ffi::{c_int, c_void},
-    ptr::NonNull,
+    ptr::{NonNull, null_mut},
     rc::Rc,
+    time::Duration,
 };
 
 use anyhow::{Context as _, anyhow};
 
-use crate::{Connection, egl, gfx, gl, wayland};
+use crate::{
+    Connection, egl,
+    gfx::{self, Rect, Size},
+    gl, wayland,
+};
+
+// https://github.com/torvalds/linux/blob/231825b2e1ff6ba799c5eaf396d3ab2354e37c6b/include/uapi/drm/drm_fourcc.h
 
+const DRM_FORMAT_RGB565: u32 = 0x36314752;
 const DRM_FORMAT_XRGB8888: u32 = 0x34325258;
+const DRM_FORMAT_XBGR8888: u32 = 0x34324258;
+const DRM_FORMAT_ARGB8888: u32 = 0x34325241;
+const DRM_FORMAT_ABGR8888: u32 = 0x34324241;
+const DRM_FORMAT_XRGB2101010: u32 = 0x30335258;
+const DRM_FORMAT_XBGR2101010: u32 = 0x30334258;
+const DRM_FORMAT_ARGB2101010: u32 = 0x30335241;
+const DRM_FORMAT_ABGR2101010: u32 = 0x30334241;
+
+fn texture_format_from_fourcc(fourcc: u32) -> anyhow::Result<gfx::TextureFormat> {
+    use gfx::TextureFormat::*;
+    Ok(match fourcc {
+        DRM_FORMAT_RGB565 => R5g6b5Unorm,
+        DRM_FORMAT_XRGB8888 => Bgrx8Unorm,
+        DRM_FORMAT_XBGR8888 => Rgbx8Unorm,
+        DRM_FORMAT_ARGB8888 => Bgra8Unorm,
+        DRM_FORMAT_ABGR8888 => Rgba8Unorm,
+        DRM_FORMAT_XRGB2101010 => Bgr10x2Unorm,
+        DRM_FORMAT_XBGR2101010 => Rgb10x2Unorm,
+        DRM_FORMAT_ARGB2101010 => Bgr10a2Unorm,
+        DRM_FORMAT_ABGR2101010 => Rgb10a2Unorm,
+        _ => return Err(anyhow!("unhandled fourcc format 0x{fourcc:08x}")),
+    })
+}
+
+/// > The drm format codes match the macros defined in drm_fourcc.h, except argb8888 and
+/// > xrgb8888.
+fn fourcc_from_shm_format(format: u32) -> u32 {
+    match format {
+        wayland::WL_SHM_FORMAT_ARGB8888 => DRM_FORMAT_ARGB8888,
+        wayland::WL_SHM_FORMAT_XRGB8888 => DRM_FORMAT_XRGB8888,
+        format => format,
+    }
+}
 
 pub enum ScreencopyState {
     Pending,
@@ -31,15 +72,13 @@ pub struct ScreencopyDmabuf {
 
 impl ScreencopyDmabuf {
     fn new(conn: &Connection, descriptor: &ScreencopyDmabufDescriptor) -> anyhow::Result<Self> {
+        let texture_format = texture_format_from_fourcc(descriptor.format)?;
         let gl_texture = unsafe {
             gl::Texture2D::new(
                 conn.libs.gl,
                 descriptor.width,
                 descriptor.height,
-                match descriptor.format {
-                    DRM_FORMAT_XRGB8888 => gfx::TextureFormat::Bgra8Unorm,
-                    format => unimplemented!("unhandled fourcc format {format}"),
-                },
+                texture_format,
                 None,
             )
         };
@@ -119,27 +158,240 @@ impl ScreencopyDmabuf {
             wl_buffer,
         })
     }
+
+    /// dmabuf path requires both the compositor (zwp_linux_dmabuf_v1) and the egl driver
+    /// (EGL_MESA_image_dma_buf_export) to cooperate; software renderers and some virtual gpus
+    /// provide neither.
+    fn is_supported(conn: &Connection) -> bool {
+        conn.globals.linux_dmabuf.is_some()
+            && unsafe {
+                conn.libs
+                    .egl_context
+                    .has_extension("EGL_MESA_image_dma_buf_export")
+            }
+    }
+}
+
+#[derive(Debug, PartialEq)]
+pub struct ScreencopyShmDescriptor {
+    pub format: u32,
+    pub width: u32,
+    pub height: u32,
+    pub stride: u32,
+}
+
+pub struct ScreencopyShm {
+    pub gl_texture: gl::Texture2D,
+    wayland_lib: &'static wayland::Lib,
+    wl_buffer: NonNull<wayland::wl_buffer>,
+    data: NonNull<c_void>,
+    size: usize,
+    width: u32,
+    height: u32,
+    stride: u32,
+}
+
+impl Drop for ScreencopyShm {
+    fn drop(&mut self) {
+        unsafe {
+            wayland::wl_buffer_destroy(self.wayland_lib, self.wl_buffer.as_ptr());
+            libc::munmap(self.data.as_ptr(), self.size);
+        }
+    }
+}
+
+impl ScreencopyShm {
+    fn new(conn: &Connection, descriptor: &ScreencopyShmDescriptor) -> anyhow::Result<Self> {
+        let texture_format = texture_format_from_fourcc(fourcc_from_shm_format(descriptor.format))?;
+
+        let shm = conn.globals.shm.context("shm is not available")?;
+        let size = descriptor.stride as usize * descriptor.height as usize;
+
+        let fd = unsafe { libc::memfd_create(c"bscreen".as_ptr(), libc::MFD_CLOEXEC) };
+        if fd < 0 {
+            let errno = unsafe { *libc::__errno_location() };
+            return Err(anyhow!("could not create memfd, errno {}", errno));
+        }
+        if unsafe { libc::ftruncate(fd, size as _) } < 0 {
+            let errno = unsafe { *libc::__errno_location() };
+            unsafe { libc::close(fd) };
+            return Err(anyhow!("could not truncate memfd, errno {}", errno));
+        }
+        let data = unsafe {
+            libc::mmap(
+                null_mut(),
+                size,
+                libc::PROT_READ | libc::PROT_WRITE,
+                libc::MAP_SHARED,
+                fd,
+                0,
+            )
+        };
+        if data == libc::MAP_FAILED {
+            let errno = unsafe { *libc::__errno_location() };
+            unsafe { libc::close(fd) };
+            return Err(anyhow!("could not mmap memfd, errno {}", errno));
+        }
+        let data = NonNull::new(data).unwrap();
+
+        // NOTE: libwayland dups the fd when marshaling, it is fine to close ours right after the
+        // pool is created.
+        let pool = unsafe { wayland::wl_shm_create_pool(conn.libs.wayland, shm, fd, size as i32) };
+        unsafe { libc::close(fd) };
+        if pool.is_null() {
+            unsafe { libc::munmap(data.as_ptr(), size) };
+            return Err(anyhow!("could not create shm pool"));
+        }
+        let wl_buffer = unsafe {
+            let wl_buffer = wayland::wl_shm_pool_create_buffer(
+                conn.libs.wayland,
+                pool,
+                0,
+                descriptor.width as i32,
+                descriptor.height as i32,
+                descriptor.stride as i32,
+                descriptor.format,
+            );
+            // > The pool will only be destroyed when all buffers created from it are destroyed.
+            wayland::wl_shm_pool_destroy(conn.libs.wayland, pool);
+            wl_buffer
+        };
+        let Some(wl_buffer) = NonNull::new(wl_buffer) else {
+            unsafe { libc::munmap(data.as_ptr(), size) };
+            return Err(anyhow!("could not create shm buffer"));
+        };
+
+        let gl_texture = unsafe {
+            gl::Texture2D::new(
+                conn.libs.gl,
+                descriptor.width,
+                descriptor.height,
+                texture_format,
+                None,
+            )
+        };
+
+        Ok(Self {
+            gl_texture,
+            wayland_lib: conn.libs.wayland,
+            wl_buffer,
+            data,
+            size,
+            width: descriptor.width,
+            height: descriptor.height,
+            stride: descriptor.stride,
+        })
+    }
+
+    /// copies contents of the shm buffer into the gl texture. must be called after the frame is
+    /// ready.
+    unsafe fn upload(&self, gl_lib: &'static gl::Lib) {
+        let pixels = std::slice::from_raw_parts(self.data.as_ptr() as *const u8, self.size);
+
+        // NOTE: gles2 does not have GL_UNPACK_ROW_LENGTH, rows must be tightly packed.
+        let row_size = self.width as usize * self.gl_texture.format_desc.block_size as usize;
+        let mut packed: Vec<u8> = Vec::with_capacity(row_size * self.height as usize);
+        for row in pixels.chunks_exact(self.stride as usize) {
+            packed.extend_from_slice(&row[..row_size]);
+        }
+
+        gl_lib.BindTexture(gl::sys::TEXTURE_2D, self.gl_texture.handle);
+        gl_lib.PixelStorei(
+            gl::sys::UNPACK_ALIGNMENT,
+            self.gl_texture.format_desc.block_size,
+        );
+        gl_lib.TexSubImage2D(
+            gl::sys::TEXTURE_2D,
+            0,
+            0,
+            0,
+            self.width as _,
+            self.height as _,
+            self.gl_texture.format_desc.format,
+            self.gl_texture.format_desc.ty,
+            packed.as_ptr() as _,
+        );
+    }
+}
+
+pub enum ScreencopyBuffer {
+    Dmabuf(ScreencopyDmabuf),
+    Shm(ScreencopyShm),
+}
+
+impl ScreencopyBuffer {
+    pub fn gl_texture(&self) -> &gl::Texture2D {
+        match self {
+            Self::Dmabuf(dmabuf) => &dmabuf.gl_texture,
+            Self::Shm(shm) => &shm.gl_texture,
+        }
+    }
+
+    fn wl_buffer(&self) -> NonNull<wayland::wl_buffer> {
+        match self {
+            Self::Dmabuf(dmabuf) => dmabuf.wl_buffer,
+            Self::Shm(shm) => shm.wl_buffer,
+        }
+    }
 }
 
 pub struct Screencopy {
     conn: Rc<Connection>,
     output: NonNull<wayland::wl_output>,
+    overlay_cursor: bool,
 
     pub state: ScreencopyState,
+    pub flags: u32,
     pub dmabuf_desc: Option<ScreencopyDmabufDescriptor>,
-    pub dmabuf: Option<ScreencopyDmabuf>,
+    pub shm_desc: Option<ScreencopyShmDescriptor>,
+    pub buffer: Option<ScreencopyBuffer>,
+    /// wall clock time at which the captured frame was presented, since unix epoch.
+    pub presented_at: Option<Duration>,
+}
+
+unsafe extern "C" fn handle_flags(
+    data: *mut c_void,
+    _zwlr_screencopy_frame_v1: *mut wayland::zwlr_screencopy_frame_v1,
+    flags: u32,
+) {
+    log::debug!("zwlr_screencopy_frame_v1_listener.flags");
+
+    let screencopy = &mut *(data as *mut Screencopy);
+    screencopy.flags = flags;
+}
+
+fn clock_now(clock: libc::clockid_t) -> Duration {
+    let mut ts: libc::timespec = unsafe { std::mem::zeroed() };
+    unsafe { libc::clock_gettime(clock, &mut ts) };
+    Duration::new(ts.tv_sec as u64, ts.tv_nsec as u32)
+}
+
+/// the protocol says that presentation timestamps may have an arbitrary offset, in practice
+/// compositors use the monotonic clock (like presentation-time does). anything that does not look
+/// like a recent monotonic time is discarded.
+fn presentation_to_unix(presented_at: Duration) -> Option<Duration> {
+    let age = clock_now(libc::CLOCK_MONOTONIC).checked_sub(presented_at)?;
+    if age > Duration::from_secs(10) {
+        return None;
+    }
+    clock_now(libc::CLOCK_REALTIME).checked_sub(age)
 }
 
 unsafe extern "C" fn handle_ready(
     data: *mut c_void,
     _zwlr_screencopy_frame_v1: *mut wayland::zwlr_screencopy_frame_v1,
-    _tv_sec_hi: u32,
-    _tv_sec_lo: u32,
-    _tv_nsec: u32,
+    tv_sec_hi: u32,
+    tv_sec_lo: u32,
+    tv_nsec: u32,
 ) {
     log::debug!("zwlr_screencopy_frame_v1_listener.ready");
 
     let screencopy = &mut *(data as *mut Screencopy);
+    let tv_sec = ((tv_sec_hi as u64) << 32) | tv_sec_lo as u64;
+    screencopy.presented_at = presentation_to_unix(Duration::new(tv_sec, tv_nsec));
+    if let Some(ScreencopyBuffer::Shm(shm)) = screencopy.buffer.as_ref() {
+        shm.upload(screencopy.conn.libs.gl);
+    }
     screencopy.state = ScreencopyState::Ready;
 }
 
@@ -153,6 +405,37 @@ unsafe extern "C" fn handle_failed(
     screencopy.state = ScreencopyState::Failed;
 }
 
+unsafe extern "C" fn handle_buffer(
+    data: *mut c_void,
+    _zwlr_screencopy_frame_v1: *mut wayland::zwlr_screencopy_frame_v1,
+    format: u32,
+    width: u32,
+    height: u32,
+    stride: u32,
+) {
+    log::debug!("zwlr_screencopy_frame_v1_listener.buffer");
+
+    let screencopy = &mut *(data as *mut Screencopy);
+
+    let next_desc = ScreencopyShmDescriptor {
+        format,
+        width,
+        height,
+        stride,
+    };
+    if screencopy
+        .shm_desc
+        .as_ref()
+        .is_some_and(|prev_desc| prev_desc.eq(&next_desc))
+    {
+        return;
+    }
+    screencopy.shm_desc = Some(next_desc);
+    if let Some(ScreencopyBuffer::Shm(_)) = screencopy.buffer.as_ref() {
+        _ = screencopy.buffer.take();
+    }
+}
+
 unsafe extern "C" fn handle_linux_dmabuf(
     data: *mut c_void,
     _zwlr_screencopy_frame_v1: *mut wayland::zwlr_screencopy_frame_v1,
@@ -177,7 +460,9 @@ unsafe extern "C" fn handle_linux_dmabuf(
         return;
     }
     screencopy.dmabuf_desc = Some(next_desc);
-    _ = screencopy.dmabuf.take();
+    if let Some(ScreencopyBuffer::Dmabuf(_)) = screencopy.buffer.as_ref() {
+        _ = screencopy.buffer.take();
+    }
 }
 
 unsafe extern "C" fn handle_buffer_done(
@@ -188,21 +473,29 @@ unsafe extern "C" fn handle_buffer_done(
 
     let screencopy = &mut *(data as *mut Screencopy);
 
-    let dmabuf = screencopy.dmabuf.get_or_insert_with(|| {
-        ScreencopyDmabuf::new(&screencopy.conn, screencopy.dmabuf_desc.as_ref().unwrap())
-            .expect("could not create screencopy dmabuf")
-    });
+    let wl_buffer = match screencopy.get_or_create_buffer() {
+        Ok(buffer) => buffer.wl_buffer(),
+        Err(err) => {
+            log::error!("could not create screencopy buffer: {err:?}");
+            wayland::zwlr_screencopy_frame_v1_destroy(
+                screencopy.conn.libs.wayland,
+                zwlr_screencopy_frame_v1,
+            );
+            screencopy.state = ScreencopyState::Failed;
+            return;
+        }
+    };
     wayland::zwlr_screencopy_frame_v1_copy(
         screencopy.conn.libs.wayland,
         zwlr_screencopy_frame_v1,
-        dmabuf.wl_buffer.as_ptr(),
+        wl_buffer.as_ptr(),
     );
 }
 
 const ZWLR_SCREENCOPY_FRAME_V1_LISTENER: wayland::zwlr_screencopy_frame_v1_listener =
     wayland::zwlr_screencopy_frame_v1_listener {
-        buffer: wayland::noop_listener!(),
-        flags: wayland::noop_listener!(),
+        buffer: handle_buffer,
+        flags: handle_flags,
         ready: handle_ready,
         failed: handle_failed,
         damage: wayland::noop_listener!(),
@@ -211,17 +504,69 @@ const ZWLR_SCREENCOPY_FRAME_V1_LISTENER: wayland::zwlr_screencopy_frame_v1_liste
     };
 
 impl Screencopy {
-    pub fn new_boxed(conn: &Rc<Connection>, output: NonNull<wayland::wl_output>) -> Box<Self> {
+    pub fn new_boxed(
+        conn: &Rc<Connection>,
+        output: NonNull<wayland::wl_output>,
+        overlay_cursor: bool,
+    ) -> Box<Self> {
         Box::new(Self {
             conn: Rc::clone(conn),
             output,
+            overlay_cursor,
 
             state: ScreencopyState::Pending,
+            flags: 0,
             dmabuf_desc: None,
-            dmabuf: None,
+            shm_desc: None,
+            buffer: None,
+            presented_at: None,
         })
     }
 
+    /// prefers dmabuf, falls back to shm if dmabuf is not supported or could not be created.
+    fn get_or_create_buffer(&mut self) -> anyhow::Result<&ScreencopyBuffer> {
+        if self.buffer.is_none() && ScreencopyDmabuf::is_supported(&self.conn) {
+            if let Some(dmabuf_desc) = &self.dmabuf_desc {
+                match ScreencopyDmabuf::new(&self.conn, dmabuf_desc) {
+                    Ok(dmabuf) => self.buffer = Some(ScreencopyBuffer::Dmabuf(dmabuf)),
+                    Err(err) if self.shm_desc.is_some() => {
+                        log::warn!(
+                            "could not create screencopy dmabuf, falling back to shm: {err:?}"
+                        );
+                    }
+                    Err(err) => return Err(err),
+                }
+            }
+        }
+
+        if self.buffer.is_none() {
+            let shm_desc = self
+                .shm_desc
+                .as_ref()
+                .context("compositor did not advertise any usable buffer")?;
+            self.buffer = Some(ScreencopyBuffer::Shm(ScreencopyShm::new(
+                &self.conn, shm_desc,
+            )?));
+        }
+
+        Ok(self.buffer.as_ref().unwrap())
+    }
+
+    pub fn y_invert(&self) -> bool {
+        self.flags & wayland::ZWLR_SCREENCOPY_FRAME_V1_FLAGS_Y_INVERT != 0
+    }
+
+    /// size of the buffer that the frame was copied into.
+    pub fn buffer_size(&self) -> Option<Size> {
+        match self.buffer.as_ref()? {
+            ScreencopyBuffer::Dmabuf(_) => {
+                let desc = self.dmabuf_desc.as_ref()?;
+                Some(Size::new(desc.width, desc.height))
+            }
+            ScreencopyBuffer::Shm(shm) => Some(Size::new(shm.width, shm.height)),
+        }
+    }
+
     pub unsafe fn capture(&mut self) -> anyhow::Result<()> {
         let screencopy_manager = self
             .conn
@@ -231,17 +576,42 @@ impl Screencopy {
         let screencopy_frame = wayland::zwlr_screencopy_manager_v1_capture_output(
             self.conn.libs.wayland,
             screencopy_manager,
-            // TODO: configurable cursor capture
-            1,
+            self.overlay_cursor as i32,
+            self.output.as_ptr(),
+        );
+        self.listen(screencopy_frame);
+
+        Ok(())
+    }
+
+    /// region is in output logical coordinates.
+    pub unsafe fn capture_region(&mut self, region: Rect) -> anyhow::Result<()> {
+        let screencopy_manager = self
+            .conn
+            .globals
+            .screencopy_manager
+            .context("screencopy manager is not available")?;
+        let screencopy_frame = wayland::zwlr_screencopy_manager_v1_capture_output_region(
+            self.conn.libs.wayland,
+            screencopy_manager,
+            self.overlay_cursor as i32,
             self.output.as_ptr(),
+            region.min.x as i32,
+            region.min.y as i32,
+            region.width() as i32,
+            region.height() as i32,
         );
+        self.listen(screencopy_frame);
+
+        Ok(())
+    }
+
+    unsafe fn listen(&mut self, screencopy_frame: *mut wayland::zwlr_screencopy_frame_v1) {
         (self.conn.libs.wayland.wl_proxy_add_listener)(
             screencopy_frame as *mut wayland::wl_proxy,
             &ZWLR_SCREENCOPY_FRAME_V1_LISTENER as *const wayland::zwlr_screencopy_frame_v1_listener
                 as _,
             self as *mut Self as *mut c_void,
         );
-
-        Ok(())
     }
 }
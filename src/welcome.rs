@@ -13,6 +13,10 @@ pub struct WelcomeUpdateData<'a> {
     pub view_rect: Rect,
     pub any_crop_has_selection: bool,
     pub this_screen_focused: bool,
+    /// the selected region is printed instead of being captured.
+    pub select: bool,
+    /// the color picker or annotation tools are active, they draw their own hints.
+    pub other_mode_active: bool,
     pub font_provider: &'a FontProvider,
     pub font_handle: Handle<Font>,
 }
@@ -40,7 +44,7 @@ impl Default for Welcome {
 
 impl Welcome {
     pub fn update(&mut self, _event: &Event, data: WelcomeUpdateData) {
-        if data.any_crop_has_selection || !data.this_screen_focused {
+        if data.any_crop_has_selection || data.other_mode_active || !data.this_screen_focused {
             self.text_layout.clear();
             return;
         }
@@ -56,17 +60,32 @@ impl Welcome {
             ..fontdue::layout::LayoutSettings::default()
         });
 
+        let text = if data.select {
+            concat!(
+                "to select a region, click and hold your mouse or trackpad button while dragging the crosshair.\n",
+                "to select the entire screen, press ctrl+a.\n",
+                "to confirm the selection, press enter.\n",
+                "to undo or redo, press ctrl+z or ctrl+shift+z.\n",
+                "to exit, press esc.",
+            )
+        } else {
+            concat!(
+                "to select a region, click and hold your mouse or trackpad button while dragging the crosshair.\n",
+                "to select the entire screen, press ctrl+a.\n",
+                "to show or hide the cursor, press ctrl+p.\n",
+                "to pick a color, press ctrl+i.\n",
+                "to annotate, press ctrl+d.\n",
+                "to undo or redo, press ctrl+z or ctrl+shift+z.\n",
+                "to save a screenshot to the clipboard, press ctrl+c.\n",
+                "to save a screenshot to a file, press ctrl+s.\n",
+                "to exit, press esc.",
+            )
+        };
+
         let font = data.font_provider.get_font(data.font_handle);
         self.text_layout.append(
             &[&font.inner],
-            &fontdue::layout::TextStyle::new(
-                concat!(
-                    "to select a region, click and hold your mouse or trackpad button while dragging the crosshair.\n",
-                    "to select the entire screen, press ctrl+a.\n",
-                    "to save a screenshot to the clipboard, press ctrl+c.\n",
-                    "to exit, press esc.",
-                ),
-                font.size, 0),
+            &fontdue::layout::TextStyle::new(text, font.size, 0),
         );
     }
 
@@ -9,11 +9,13 @@ use crate::dynlib::{DynLib, opaque_struct};
 use crate::input::KeyboardMods;
 
 pub const XKB_MOD_NAME_CTRL: &[u8] = b"Control\0";
+pub const XKB_MOD_NAME_SHIFT: &[u8] = b"Shift\0";
 
 opaque_struct!(xkb_context);
 opaque_struct!(xkb_keymap);
 opaque_struct!(xkb_state);
 
+pub type xkb_keycode_t = u32;
 pub type xkb_layout_index_t = u32;
 pub type xkb_mod_index_t = u32;
 pub type xkb_mod_mask_t = u32;
@@ -68,12 +70,20 @@ pub struct Lib {
         format: xkb_keymap_format,
         flags: xkb_keymap_compile_flags,
     ) -> *mut xkb_keymap,
+    pub xkb_keymap_key_repeats:
+        unsafe extern "C" fn(keymap: *mut xkb_keymap, key: xkb_keycode_t) -> c_int,
     pub xkb_keymap_unref: unsafe extern "C" fn(keymap: *mut xkb_keymap),
     pub xkb_state_mod_index_is_active: unsafe extern "C" fn(
         state: *mut xkb_state,
         idx: xkb_mod_index_t,
         ty: xkb_state_component,
     ) -> c_int,
+    pub xkb_state_key_get_utf8: unsafe extern "C" fn(
+        state: *mut xkb_state,
+        key: xkb_keycode_t,
+        buffer: *mut c_char,
+        size: usize,
+    ) -> c_int,
     pub xkb_state_new: unsafe extern "C" fn(keymap: *mut xkb_keymap) -> *mut xkb_state,
     pub xkb_state_unref: unsafe extern "C" fn(state: *mut xkb_state),
     pub xkb_state_update_mask: unsafe extern "C" fn(
@@ -97,8 +107,10 @@ impl Lib {
             xkb_context_unref: lib.lookup(b"xkb_context_unref\0")?,
             xkb_keymap_mod_get_index: lib.lookup(b"xkb_keymap_mod_get_index\0")?,
             xkb_keymap_new_from_string: lib.lookup(b"xkb_keymap_new_from_string\0")?,
+            xkb_keymap_key_repeats: lib.lookup(b"xkb_keymap_key_repeats\0")?,
             xkb_keymap_unref: lib.lookup(b"xkb_keymap_unref\0")?,
             xkb_state_mod_index_is_active: lib.lookup(b"xkb_state_mod_index_is_active\0")?,
+            xkb_state_key_get_utf8: lib.lookup(b"xkb_state_key_get_utf8\0")?,
             xkb_state_new: lib.lookup(b"xkb_state_new\0")?,
             xkb_state_unref: lib.lookup(b"xkb_state_unref\0")?,
             xkb_state_update_mask: lib.lookup(b"xkb_state_update_mask\0")?,
@@ -114,6 +126,7 @@ impl Lib {
 #[derive(Debug)]
 pub struct KeyboardModIndices {
     pub ctrl: xkb_mod_index_t,
+    pub shift: xkb_mod_index_t,
 }
 
 pub struct Context {
@@ -173,8 +186,15 @@ impl Context {
                     keymap,
                     XKB_MOD_NAME_CTRL.as_ptr() as _,
                 ),
+                shift: (xkbcommon_lib.xkb_keymap_mod_get_index)(
+                    keymap,
+                    XKB_MOD_NAME_SHIFT.as_ptr() as _,
+                ),
+            },
+            mods: KeyboardMods {
+                ctrl: false,
+                shift: false,
             },
-            mods: KeyboardMods { ctrl: false },
             xkbcommon: xkbcommon_lib,
         })
     }
@@ -203,8 +223,39 @@ impl Context {
                 self.mod_indices.ctrl,
                 xkb_state_component::XKB_STATE_MODS_EFFECTIVE,
             ) == 1;
+            self.mods.shift = (self.xkbcommon.xkb_state_mod_index_is_active)(
+                self.state,
+                self.mod_indices.shift,
+                xkb_state_component::XKB_STATE_MODS_EFFECTIVE,
+            ) == 1;
         }
     }
+
+    /// returns text that the key produces with current modifiers; control characters (e.g. of
+    /// backspace or ctrl+c) are not considered to be text.
+    pub unsafe fn key_get_utf8(&self, key: u32) -> Option<String> {
+        // NOTE: xkb keycodes are evdev scancodes offset by 8.
+        let keycode = key + 8;
+        let mut buf = [0u8; 64];
+        let len = (self.xkbcommon.xkb_state_key_get_utf8)(
+            self.state,
+            keycode,
+            buf.as_mut_ptr() as _,
+            buf.len(),
+        );
+        // NOTE: len does not include the nul terminator, the text is truncated if it does not fit.
+        let len = (len.max(0) as usize).min(buf.len() - 1);
+        let text = std::str::from_utf8(&buf[..len]).ok()?;
+        if text.is_empty() || text.chars().any(char::is_control) {
+            return None;
+        }
+        Some(text.to_string())
+    }
+
+    /// whether the key should repeat when held, e.g. modifiers do not.
+    pub unsafe fn key_repeats(&self, key: u32) -> bool {
+        (self.xkbcommon.xkb_keymap_key_repeats)(self.keymap, key + 8) == 1
+    }
 }
 
 impl Drop for Context {